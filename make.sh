@@ -34,6 +34,11 @@ update)
 	source $SCRIPT/update.sh
 	update_deps
 	;;
+wakeups)
+	source $SCRIPT/wakeups.sh
+	shift
+	measure_wakeups $@
+	;;
 help)
 	echo "./make.sh:
     build:
@@ -44,7 +49,9 @@ help)
     fix:
         fix codes of fas-rs
     update:
-        recursive update all depended crates"
+        recursive update all depended crates
+    wakeups [secs]:
+        count wakeups of fas-rs running on the adb device, 60s by default"
 	;;
 *)
 	echo Illegal parameter: $1 >&2
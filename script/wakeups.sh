@@ -0,0 +1,35 @@
+#!/bin/bash
+#
+# Copyright 2023 shadow3aaa@gitbub.com
+#
+# Licensed under the Apache License, Version 2.0 (the "License");
+# you may not use this file except in compliance with the License.
+#  You may obtain a copy of the License at
+#
+#     http://www.apache.org/licenses/LICENSE-2.0
+#
+# Unless required by applicable law or agreed to in writing, software
+# distributed under the License is distributed on an "AS IS" BASIS,
+# WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+# See the License for the specific language governing permissions and
+# limitations under the License.
+count_switches() {
+	adb shell "cat /proc/$1/task/*/status" | awk '/^voluntary_ctxt_switches/ { s += $2 } END { print s }'
+}
+
+# 统计fas-rs所有线程在一段时间内的主动上下文切换次数, 即唤醒次数
+measure_wakeups() {
+	local SECS=${1:-60}
+	local PID=$(adb shell pidof fas-rs | tr -d '\r')
+
+	if [[ -z $PID ]]; then
+		echo "fas-rs is not running" >&2
+		return 1
+	fi
+
+	local BEFORE=$(count_switches $PID)
+	sleep $SECS
+	local AFTER=$(count_switches $PID)
+
+	echo "fas-rs wakeups in ${SECS}s: $((AFTER - BEFORE))"
+}
@@ -0,0 +1,269 @@
+/* Copyright 2023 shadow3aaa@gitbub.com
+*
+*  Licensed under the Apache License, Version 2.0 (the "License");
+*  you may not use this file except in compliance with the License.
+*  You may obtain a copy of the License at
+*
+*      http://www.apache.org/licenses/LICENSE-2.0
+*
+*  Unless required by applicable law or agreed to in writing, software
+*  distributed under the License is distributed on an "AS IS" BASIS,
+*  WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+*  See the License for the specific language governing permissions and
+*  limitations under the License. */
+use std::{
+    ffi::OsStr,
+    fs,
+    path::{Path, PathBuf},
+};
+
+use anyhow::Result;
+use likely_stable::LikelyOption;
+use log::info;
+
+use crate::misc::{lock_write, unlock_write};
+
+pub type Freq = usize; // 单位: 由设备节点决定(khz/mhz/mbps)
+
+const DEVFREQ: &str = "sys/class/devfreq";
+const BUS_DCVS: &str = "sys/devices/system/cpu/bus_dcvs";
+const DEVFREQ_PATTERNS: [&str; 6] = ["bw", "llcc", "ddr", "dvfsrc", "bus", "memlat"];
+
+#[derive(Debug)]
+struct BusNode {
+    path: PathBuf,
+    freqs: Vec<Freq>,
+    default_freqs: (String, String),
+}
+
+impl BusNode {
+    fn new<P: AsRef<Path>, T: AsRef<Path>>(p: P, table: T) -> Option<Self> {
+        let path = p.as_ref();
+
+        let mut freqs: Vec<Freq> = fs::read_to_string(table.as_ref().join("available_frequencies"))
+            .ok()?
+            .split_whitespace()
+            .filter_map(|f| f.parse().ok())
+            .collect();
+        freqs.sort_unstable();
+        freqs.dedup();
+
+        if freqs.is_empty() {
+            return None;
+        }
+
+        let min = fs::read_to_string(path.join("min_freq")).ok()?;
+        let max = fs::read_to_string(path.join("max_freq")).ok()?;
+
+        Some(Self {
+            path: path.to_path_buf(),
+            freqs,
+            default_freqs: (min.trim().to_string(), max.trim().to_string()),
+        })
+    }
+
+    fn set_level(&self, level: f64, boost: bool) -> Result<()> {
+        let pos = (level.clamp(0.0, 1.0) * (self.freqs.len() - 1) as f64).ceil() as usize;
+        let freq = self.freqs[pos].to_string();
+
+        let first_freq = self.freqs[0].to_string();
+        let last_freq = self.freqs.last().copied().unwrap().to_string();
+
+        if boost {
+            lock_write(self.path.join("max_freq"), last_freq)?;
+            lock_write(self.path.join("min_freq"), freq)?;
+        } else {
+            lock_write(self.path.join("min_freq"), first_freq)?;
+            lock_write(self.path.join("max_freq"), freq)?;
+        }
+
+        Ok(())
+    }
+
+    fn reset(&self) -> Result<()> {
+        let (min, max) = &self.default_freqs;
+
+        unlock_write(self.path.join("max_freq"), max)?;
+        unlock_write(self.path.join("min_freq"), min)?;
+
+        Ok(())
+    }
+}
+
+// ddr/llcc等带宽调速器, 跟随cpu的性能等级一起限制/提升
+#[derive(Debug)]
+pub struct BusCommon {
+    nodes: Vec<BusNode>,
+}
+
+impl BusCommon {
+    pub fn new() -> Option<Self> {
+        Self::from_root("/")
+    }
+
+    // root为sysfs所在的根目录, 方便在测试中替换
+    pub fn from_root<P: AsRef<Path>>(root: P) -> Option<Self> {
+        let root = root.as_ref();
+        let mut nodes = Self::devfreq_nodes(root);
+        nodes.extend(Self::bus_dcvs_nodes(root));
+
+        if nodes.is_empty() {
+            return None;
+        }
+
+        for node in &nodes {
+            info!("Found memory bus node: {}", node.path.display());
+        }
+
+        Some(Self { nodes })
+    }
+
+    fn devfreq_nodes(root: &Path) -> Vec<BusNode> {
+        let Ok(dir) = fs::read_dir(root.join(DEVFREQ)) else {
+            return Vec::new();
+        };
+
+        dir.filter_map(|d| Some(d.ok()?.path()))
+            .filter(|p| {
+                p.file_name()
+                    .and_then_likely(OsStr::to_str)
+                    .is_some_and(|n| {
+                        !n.contains("gpu")
+                            && !n.contains("kgsl")
+                            && DEVFREQ_PATTERNS.iter().any(|b| n.contains(b))
+                    })
+            })
+            .filter_map(|p| BusNode::new(&p, &p))
+            .collect()
+    }
+
+    // bus_dcvs/<DDR|LLCC|L3>/<voter>/{min_freq, max_freq}, 频率表在上一级
+    fn bus_dcvs_nodes(root: &Path) -> Vec<BusNode> {
+        let Ok(dir) = fs::read_dir(root.join(BUS_DCVS)) else {
+            return Vec::new();
+        };
+
+        dir.filter_map(|d| Some(d.ok()?.path()))
+            .filter(|p| p.is_dir())
+            .flat_map(|bus| {
+                fs::read_dir(&bus)
+                    .into_iter()
+                    .flatten()
+                    .filter_map(|d| Some(d.ok()?.path()))
+                    .filter(|p| p.is_dir())
+                    .filter_map(|voter| BusNode::new(voter, &bus))
+                    .collect::<Vec<_>>()
+            })
+            .collect()
+    }
+
+    pub fn set_level(&self, level: f64, boost: bool) {
+        for node in &self.nodes {
+            let _ = node.set_level(level, boost);
+        }
+    }
+
+    pub fn init_default(&self) {
+        for node in &self.nodes {
+            let _ = node.reset();
+        }
+    }
+}
+
+// 测试用的带宽节点: 一个devfreq的llcc节点和一个bus_dcvs的DDR投票节点
+#[cfg(test)]
+pub fn bus_nodes(fixture: &crate::misc::Fixture) {
+    let llcc = format!("{DEVFREQ}/soc:qcom,cpu-cpu-llcc-bw");
+    fixture.write(
+        format!("{llcc}/available_frequencies"),
+        "762 2086 4577 6515\n",
+    );
+    fixture.write(format!("{llcc}/min_freq"), "762\n");
+    fixture.write(format!("{llcc}/max_freq"), "6515\n");
+
+    let ddr = format!("{BUS_DCVS}/DDR");
+    fixture.write(
+        format!("{ddr}/available_frequencies"),
+        "547000 1017000 1555000 2092000 3196000\n",
+    );
+    fixture.write(format!("{ddr}/boost/min_freq"), "547000\n");
+    fixture.write(format!("{ddr}/boost/max_freq"), "3196000\n");
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::misc::Fixture;
+
+    const LLCC: &str = "sys/class/devfreq/soc:qcom,cpu-cpu-llcc-bw";
+    const DDR: &str = "sys/devices/system/cpu/bus_dcvs/DDR/boost";
+
+    fn freqs(fixture: &Fixture, node: &str) -> (String, String) {
+        (
+            fixture.read(format!("{node}/min_freq")),
+            fixture.read(format!("{node}/max_freq")),
+        )
+    }
+
+    fn paths(bus: &BusCommon) -> Vec<PathBuf> {
+        let mut paths: Vec<_> = bus.nodes.iter().map(|n| n.path.clone()).collect();
+        paths.sort();
+        paths
+    }
+
+    #[test]
+    fn discovery() {
+        let fixture = Fixture::new("bus-discovery");
+        assert!(BusCommon::from_root(fixture.path()).is_none());
+
+        bus_nodes(&fixture);
+
+        // gpu带宽, 不匹配的设备和没有频率表的节点都被跳过
+        for (name, table) in [
+            ("soc:qcom,gpubw", true),
+            ("3d00000.qcom,kgsl-3d0", true),
+            ("1d84000.ufshc", true),
+            ("soc:qcom,memlat-cpu0", false),
+        ] {
+            let node = format!("{DEVFREQ}/{name}");
+            if table {
+                fixture.write(format!("{node}/available_frequencies"), "100 200\n");
+            }
+            fixture.write(format!("{node}/min_freq"), "100\n");
+            fixture.write(format!("{node}/max_freq"), "200\n");
+        }
+        fixture.write(format!("{BUS_DCVS}/DDR/available_frequencies.bak"), "");
+
+        let bus = BusCommon::from_root(fixture.path()).unwrap();
+        assert_eq!(
+            paths(&bus),
+            [fixture.path().join(LLCC), fixture.path().join(DDR)]
+        );
+        assert_eq!(bus.nodes.iter().map(|n| n.freqs.len()).sum::<usize>(), 9);
+    }
+
+    #[test]
+    fn follow_level() {
+        let fixture = Fixture::new("bus-level");
+        bus_nodes(&fixture);
+        let bus = BusCommon::from_root(fixture.path()).unwrap();
+
+        // 限制上限, 向上取整到频率表中的一项
+        bus.set_level(0.5, false);
+        assert_eq!(freqs(&fixture, LLCC), ("762".into(), "4577".into()));
+        assert_eq!(freqs(&fixture, DDR), ("547000".into(), "1555000".into()));
+
+        bus.set_level(0.0, false);
+        assert_eq!(freqs(&fixture, LLCC), ("762".into(), "762".into()));
+
+        // fas_boost时抬高下限
+        bus.set_level(0.6, true);
+        assert_eq!(freqs(&fixture, LLCC), ("4577".into(), "6515".into()));
+        assert_eq!(freqs(&fixture, DDR), ("2092000".into(), "3196000".into()));
+
+        // 恢复启动时读到的值
+        bus.init_default();
+        assert_eq!(freqs(&fixture, LLCC), ("762".into(), "6515".into()));
+        assert_eq!(freqs(&fixture, DDR), ("547000".into(), "3196000".into()));
+    }
+}
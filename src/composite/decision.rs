@@ -0,0 +1,122 @@
+/* Copyright 2023 shadow3aaa@gitbub.com
+*
+*  Licensed under the Apache License, Version 2.0 (the "License");
+*  you may not use this file except in compliance with the License.
+*  You may obtain a copy of the License at
+*
+*      http://www.apache.org/licenses/LICENSE-2.0
+*
+*  Unless required by applicable law or agreed to in writing, software
+*  distributed under the License is distributed on an "AS IS" BASIS,
+*  WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+*  See the License for the specific language governing permissions and
+*  limitations under the License. */
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Action {
+    Limit,
+    Release,
+    ReleaseMax,
+}
+
+// 超过此使用率认为资源已经饱和, 是卡顿的瓶颈
+pub const SATURATED_USAGE: f64 = 0.85;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Decision {
+    pub action: Action,
+    pub usages: Vec<Option<f64>>,
+    pub targets: Vec<usize>,
+}
+
+impl Decision {
+    pub fn decide(action: Action, usages: Vec<Option<f64>>) -> Self {
+        let targets = match action {
+            Action::ReleaseMax => (0..usages.len()).collect(),
+            Action::Release => Self::bottlenecks(&usages),
+            Action::Limit => Self::limitables(&usages),
+        };
+
+        Self {
+            action,
+            usages,
+            targets,
+        }
+    }
+
+    // 只提升饱和的资源, 没有饱和的资源时提升最忙的一个, 完全未知时全部提升
+    fn bottlenecks(usages: &[Option<f64>]) -> Vec<usize> {
+        let saturated: Vec<_> = usages
+            .iter()
+            .enumerate()
+            .filter(|(_, u)| u.is_some_and(|u| u >= SATURATED_USAGE))
+            .map(|(i, _)| i)
+            .collect();
+
+        if !saturated.is_empty() {
+            return saturated;
+        }
+
+        usages
+            .iter()
+            .enumerate()
+            .filter_map(|(i, u)| Some((i, (*u)?)))
+            .max_by(|(_, a), (_, b)| a.total_cmp(b))
+            .map_or_else(|| (0..usages.len()).collect(), |(i, _)| vec![i])
+    }
+
+    // 饱和的资源再限制就会卡顿, 只限制未饱和或未知的
+    fn limitables(usages: &[Option<f64>]) -> Vec<usize> {
+        usages
+            .iter()
+            .enumerate()
+            .filter(|(_, u)| u.is_none_or(|u| u < SATURATED_USAGE))
+            .map(|(i, _)| i)
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Action, Decision};
+
+    const SATURATED: [Option<f64>; 3] = [Some(0.9), Some(0.3), None];
+    const NONE_SATURATED: [Option<f64>; 3] = [Some(0.5), Some(0.7), Some(0.2)];
+    const UNKNOWN: [Option<f64>; 2] = [None, None];
+
+    fn targets(action: Action, usages: &[Option<f64>]) -> Vec<usize> {
+        Decision::decide(action, usages.to_vec()).targets
+    }
+
+    #[test]
+    fn release_only_bottlenecks() {
+        assert_eq!(targets(Action::Release, &SATURATED), vec![0]);
+        assert_eq!(targets(Action::Release, &NONE_SATURATED), vec![1]);
+        assert_eq!(targets(Action::Release, &UNKNOWN), vec![0, 1]);
+        assert_eq!(
+            targets(Action::Release, &[Some(0.95), Some(0.85)]),
+            vec![0, 1]
+        );
+    }
+
+    #[test]
+    fn limit_skips_saturated() {
+        assert_eq!(targets(Action::Limit, &SATURATED), vec![1, 2]);
+        assert_eq!(targets(Action::Limit, &NONE_SATURATED), vec![0, 1, 2]);
+        assert_eq!(targets(Action::Limit, &UNKNOWN), vec![0, 1]);
+    }
+
+    #[test]
+    fn release_max_targets_all() {
+        assert_eq!(targets(Action::ReleaseMax, &SATURATED), vec![0, 1, 2]);
+        assert_eq!(targets(Action::ReleaseMax, &NONE_SATURATED), vec![0, 1, 2]);
+        assert_eq!(targets(Action::ReleaseMax, &UNKNOWN), vec![0, 1]);
+    }
+
+    #[test]
+    fn decision_keeps_trace() {
+        let decision = Decision::decide(Action::Limit, SATURATED.to_vec());
+
+        assert_eq!(decision.action, Action::Limit);
+        assert_eq!(decision.usages, SATURATED.to_vec());
+    }
+}
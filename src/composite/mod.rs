@@ -0,0 +1,249 @@
+/* Copyright 2023 shadow3aaa@gitbub.com
+*
+*  Licensed under the Apache License, Version 2.0 (the "License");
+*  you may not use this file except in compliance with the License.
+*  You may obtain a copy of the License at
+*
+*      http://www.apache.org/licenses/LICENSE-2.0
+*
+*  Unless required by applicable law or agreed to in writing, software
+*  distributed under the License is distributed on an "AS IS" BASIS,
+*  WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+*  See the License for the specific language governing permissions and
+*  limitations under the License. */
+mod decision;
+
+#[cfg(debug_assertions)]
+use log::debug;
+
+use crate::framework::{prelude::*, Result};
+
+use decision::{Action, Decision};
+
+#[derive(Default)]
+pub struct CompositeController {
+    controllers: Vec<Box<dyn PerformanceController>>,
+}
+
+impl CompositeController {
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    #[must_use]
+    pub fn controller<P: PerformanceController + 'static>(mut self, c: P) -> Self {
+        self.controllers.push(Box::new(c));
+        self
+    }
+
+    fn apply(&self, action: Action, m: Mode, c: &Config) -> Result<()> {
+        let usages = self.controllers.iter().map(|c| c.usage()).collect();
+        let decision = Decision::decide(action, usages);
+
+        #[cfg(debug_assertions)]
+        debug!("composite decision: {decision:?}");
+
+        for i in decision.targets {
+            let controller = &self.controllers[i];
+
+            match action {
+                Action::Limit => controller.limit(m, c)?,
+                Action::Release => controller.release(m, c)?,
+                Action::ReleaseMax => controller.release_max(m, c)?,
+            }
+        }
+
+        Ok(())
+    }
+
+    // 初始化类操作需要作用于所有控制器, 即使其中一个失败
+    fn apply_all<F>(&self, f: F) -> Result<()>
+    where
+        F: Fn(&dyn PerformanceController) -> Result<()>,
+    {
+        let mut result = Ok(());
+
+        for controller in &self.controllers {
+            if let Err(e) = f(controller.as_ref()) {
+                result = Err(e);
+            }
+        }
+
+        result
+    }
+}
+
+impl PerformanceController for CompositeController {
+    fn limit(&self, m: Mode, c: &Config) -> Result<()> {
+        self.apply(Action::Limit, m, c)
+    }
+
+    fn release(&self, m: Mode, c: &Config) -> Result<()> {
+        self.apply(Action::Release, m, c)
+    }
+
+    fn release_max(&self, m: Mode, c: &Config) -> Result<()> {
+        self.apply(Action::ReleaseMax, m, c)
+    }
+
+    fn init_game(&self, m: Mode, c: &Config) -> Result<()> {
+        self.apply_all(|controller| controller.init_game(m, c))
+    }
+
+    fn init_default(&self, m: Mode, c: &Config) -> Result<()> {
+        self.apply_all(|controller| controller.init_default(m, c))
+    }
+
+    fn set_ceiling(&self, ceiling: f64) {
+        for controller in &self.controllers {
+            controller.set_ceiling(ceiling);
+        }
+    }
+
+    fn status(&self) -> Vec<(String, String)> {
+        self.controllers.iter().flat_map(|c| c.status()).collect()
+    }
+
+    fn usage(&self) -> Option<f64> {
+        self.controllers
+            .iter()
+            .filter_map(|c| c.usage())
+            .reduce(f64::max)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use parking_lot::Mutex;
+
+    use super::{decision::Action, CompositeController};
+    use crate::framework::{prelude::*, Result};
+
+    type Calls = Arc<Mutex<Vec<(usize, Action)>>>;
+
+    struct Mock {
+        id: usize,
+        usage: Option<f64>,
+        calls: Calls,
+    }
+
+    impl Mock {
+        fn record(&self, action: Action) {
+            self.calls.lock().push((self.id, action));
+        }
+    }
+
+    impl PerformanceController for Mock {
+        fn limit(&self, _m: Mode, _c: &Config) -> Result<()> {
+            self.record(Action::Limit);
+            Ok(())
+        }
+
+        fn release(&self, _m: Mode, _c: &Config) -> Result<()> {
+            self.record(Action::Release);
+            Ok(())
+        }
+
+        fn release_max(&self, _m: Mode, _c: &Config) -> Result<()> {
+            self.record(Action::ReleaseMax);
+            Ok(())
+        }
+
+        fn init_game(&self, _m: Mode, _c: &Config) -> Result<()> {
+            Ok(())
+        }
+
+        fn init_default(&self, _m: Mode, _c: &Config) -> Result<()> {
+            Ok(())
+        }
+
+        fn usage(&self) -> Option<f64> {
+            self.usage
+        }
+    }
+
+    fn composite(usages: &[Option<f64>]) -> (CompositeController, Calls) {
+        let calls = Calls::default();
+        let controller = usages.iter().enumerate().fold(
+            CompositeController::new(),
+            |controller, (id, usage)| {
+                controller.controller(Mock {
+                    id,
+                    usage: *usage,
+                    calls: calls.clone(),
+                })
+            },
+        );
+
+        (controller, calls)
+    }
+
+    fn applied(usages: &[Option<f64>], action: Action) -> Vec<(usize, Action)> {
+        let config = Config::new("module/games.toml", "module/games.toml").unwrap();
+        let (controller, calls) = composite(usages);
+
+        match action {
+            Action::Limit => controller.limit(Mode::Balance, &config),
+            Action::Release => controller.release(Mode::Balance, &config),
+            Action::ReleaseMax => controller.release_max(Mode::Balance, &config),
+        }
+        .unwrap();
+
+        let calls = calls.lock().clone();
+        calls
+    }
+
+    #[test]
+    fn apply_saturated() {
+        let usages = [Some(0.9), Some(0.3)];
+
+        assert_eq!(applied(&usages, Action::Limit), vec![(1, Action::Limit)]);
+        assert_eq!(
+            applied(&usages, Action::Release),
+            vec![(0, Action::Release)]
+        );
+        assert_eq!(
+            applied(&usages, Action::ReleaseMax),
+            vec![(0, Action::ReleaseMax), (1, Action::ReleaseMax)]
+        );
+    }
+
+    #[test]
+    fn apply_none_saturated() {
+        let usages = [Some(0.4), Some(0.6)];
+
+        assert_eq!(
+            applied(&usages, Action::Limit),
+            vec![(0, Action::Limit), (1, Action::Limit)]
+        );
+        assert_eq!(
+            applied(&usages, Action::Release),
+            vec![(1, Action::Release)]
+        );
+        assert_eq!(
+            applied(&usages, Action::ReleaseMax),
+            vec![(0, Action::ReleaseMax), (1, Action::ReleaseMax)]
+        );
+    }
+
+    #[test]
+    fn apply_unknown() {
+        let usages = [None, None];
+
+        for action in [Action::Limit, Action::Release, Action::ReleaseMax] {
+            assert_eq!(applied(&usages, action), vec![(0, action), (1, action)]);
+        }
+    }
+
+    #[test]
+    fn usage_is_busiest() {
+        let (controller, _) = composite(&[Some(0.2), None, Some(0.7)]);
+        assert_eq!(controller.usage(), Some(0.7));
+
+        let (controller, _) = composite(&[None]);
+        assert_eq!(controller.usage(), None);
+    }
+}
@@ -0,0 +1,190 @@
+/* Copyright 2023 shadow3aaa@gitbub.com
+*
+*  Licensed under the Apache License, Version 2.0 (the "License");
+*  you may not use this file except in compliance with the License.
+*  You may obtain a copy of the License at
+*
+*      http://www.apache.org/licenses/LICENSE-2.0
+*
+*  Unless required by applicable law or agreed to in writing, software
+*  distributed under the License is distributed on an "AS IS" BASIS,
+*  WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+*  See the License for the specific language governing permissions and
+*  limitations under the License. */
+use std::{
+    fs,
+    path::Path,
+    time::{Duration, Instant},
+};
+
+use log::info;
+
+use super::{policy::Policy, CpuCommon};
+use crate::framework::prelude::*;
+
+const REFRESH_TIME: Duration = Duration::from_secs(1);
+
+impl Policy {
+    // cpu0等不可热插拔的核心没有online节点, 视为在线
+    pub fn read_online(&self, root: &Path) -> bool {
+        self.cpus.iter().any(|cpu| {
+            fs::read_to_string(root.join(format!("sys/devices/system/cpu/cpu{cpu}/online")))
+                .map_or(true, |o| o.trim() == "1")
+        })
+    }
+}
+
+impl CpuCommon {
+    pub fn online_policies(&self) -> impl Iterator<Item = &Policy> {
+        self.policies.iter().filter(|p| p.online.get())
+    }
+
+    // 集群全部离线时policy节点失效, 暂停写入, 重新上线后恢复当前的fas状态
+    pub fn check_hotplug(&self, m: Mode, c: &Config) {
+        if self.hotplug_timer.get().elapsed() < REFRESH_TIME {
+            return;
+        }
+        self.hotplug_timer.set(Instant::now());
+
+        for policy in &self.policies {
+            let online = policy.read_online(&self.root);
+            if online == policy.online.get() {
+                continue;
+            }
+
+            policy.online.set(online);
+
+            if !online {
+                info!("Cpufreq policy{} went offline, suspended", policy.num);
+                continue;
+            }
+
+            info!("Cpufreq policy{} came back online, resumed", policy.num);
+
+            if self.in_game.get() {
+                let _ = policy.init_game(m, c);
+                let _ = policy.set_fas_freq(self.fas_freq.get());
+            } else {
+                let _ = policy.init_default();
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{cpu_common::two_clusters, misc::Fixture};
+
+    const BIG: &str = "sys/devices/system/cpu/cpufreq/policy4";
+
+    fn fixture(name: &str) -> Fixture {
+        let fixture = two_clusters(name);
+
+        set_big_online(&fixture, true);
+        fixture
+    }
+
+    fn set_big_online(fixture: &Fixture, online: bool) {
+        for cpu in 4..8 {
+            fixture.write(
+                format!("sys/devices/system/cpu/cpu{cpu}/online"),
+                if online { "1" } else { "0" },
+            );
+        }
+    }
+
+    fn expire(cpu: &CpuCommon) {
+        cpu.hotplug_timer
+            .set(Instant::now().checked_sub(REFRESH_TIME).unwrap());
+    }
+
+    fn big(cpu: &CpuCommon) -> &Policy {
+        cpu.policies.iter().find(|p| p.num == 4).unwrap()
+    }
+
+    #[test]
+    fn missing_online_node() {
+        let fixture = fixture("hotplug-node");
+        let cpu = CpuCommon::from_root(fixture.path()).unwrap();
+
+        // cpu0没有online节点
+        let little = cpu.policies.iter().find(|p| p.num == 0).unwrap();
+        assert!(little.read_online(fixture.path()));
+        assert!(big(&cpu).read_online(fixture.path()));
+
+        set_big_online(&fixture, false);
+        assert!(!big(&cpu).read_online(fixture.path()));
+    }
+
+    #[test]
+    fn offline_suspends_online_reapplies_game() {
+        let fixture = fixture("hotplug-game");
+        let config = Config::new("module/games.toml", "module/games.toml").unwrap();
+        let cpu = CpuCommon::from_root(fixture.path()).unwrap();
+
+        cpu.init_game(Mode::Powersave, &config).unwrap();
+        assert_eq!(fixture.read(format!("{BIG}/scaling_max_freq")), "2400000");
+
+        set_big_online(&fixture, false);
+        expire(&cpu);
+        cpu.check_hotplug(Mode::Powersave, &config);
+        assert!(!big(&cpu).online.get());
+        assert_eq!(cpu.online_policies().count(), 1);
+
+        // 离线期间不写入
+        fixture.write(format!("{BIG}/scaling_max_freq"), "0");
+        cpu.limit(Mode::Powersave, &config).unwrap();
+        assert_eq!(fixture.read(format!("{BIG}/scaling_max_freq")), "0");
+
+        set_big_online(&fixture, true);
+        expire(&cpu);
+        cpu.check_hotplug(Mode::Powersave, &config);
+        assert!(big(&cpu).online.get());
+        assert_eq!(
+            fixture.read(format!("{BIG}/scaling_max_freq")),
+            cpu.fas_freq.get().to_string()
+        );
+        assert_eq!(fixture.read(format!("{BIG}/scaling_min_freq")), "500000");
+    }
+
+    #[test]
+    fn offline_suspends_online_reapplies_default() {
+        let fixture = fixture("hotplug-default");
+        let config = Config::new("module/games.toml", "module/games.toml").unwrap();
+        let cpu = CpuCommon::from_root(fixture.path()).unwrap();
+
+        cpu.init_default(Mode::Powersave, &config).unwrap();
+
+        set_big_online(&fixture, false);
+        expire(&cpu);
+        cpu.check_hotplug(Mode::Powersave, &config);
+        assert!(!big(&cpu).online.get());
+
+        fixture.write(format!("{BIG}/scaling_max_freq"), "0");
+        fixture.write(format!("{BIG}/scaling_min_freq"), "0");
+
+        set_big_online(&fixture, true);
+        expire(&cpu);
+        cpu.check_hotplug(Mode::Powersave, &config);
+        assert!(big(&cpu).online.get());
+        assert_eq!(fixture.read(format!("{BIG}/scaling_max_freq")), "2400000");
+        assert_eq!(fixture.read(format!("{BIG}/scaling_min_freq")), "500000");
+        assert_eq!(big(&cpu).expected.get(), None);
+    }
+
+    #[test]
+    fn refresh_period() {
+        let fixture = fixture("hotplug-period");
+        let config = Config::new("module/games.toml", "module/games.toml").unwrap();
+        let cpu = CpuCommon::from_root(fixture.path()).unwrap();
+
+        set_big_online(&fixture, false);
+        cpu.check_hotplug(Mode::Powersave, &config);
+        assert!(big(&cpu).online.get());
+
+        expire(&cpu);
+        cpu.check_hotplug(Mode::Powersave, &config);
+        assert!(!big(&cpu).online.get());
+    }
+}
@@ -11,44 +11,83 @@
 *  WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
 *  See the License for the specific language governing permissions and
 *  limitations under the License. */
+mod hotplug;
 mod policy;
+mod topology;
+mod usage;
+mod verify;
 
-use std::{cell::Cell, collections::HashSet, ffi::OsStr, fs};
+use std::{
+    cell::Cell,
+    collections::HashSet,
+    ffi::OsStr,
+    fs,
+    path::{Path, PathBuf},
+    time::Instant,
+};
 
-use crate::framework::{prelude::*, Result as FrameworkResult};
+use crate::{
+    error::Error,
+    framework::{prelude::*, Result as FrameworkResult},
+};
 use anyhow::Result;
+use likely_stable::LikelyOption;
+use log::{info, warn};
 
 use policy::Policy;
+use usage::UsageTracker;
+use verify::Verifier;
 
 pub type Freq = usize; // 单位: khz
 
 #[derive(Debug)]
 pub struct CpuCommon {
+    root: PathBuf,
     freqs: Vec<Freq>,
     fas_freq: Cell<Freq>,
     policies: Vec<Policy>,
+    usage: UsageTracker,
+    verifier: Verifier,
+    in_game: Cell<bool>,
+    ceiling: Cell<f64>,
+    hotplug_timer: Cell<Instant>,
 }
 
 impl CpuCommon {
     pub fn new() -> Result<Self> {
-        let mut policies: Vec<_> = fs::read_dir("/sys/devices/system/cpu/cpufreq")?
+        Self::from_root("/")
+    }
+
+    // root为sysfs所在的根目录, 方便在测试中替换
+    pub fn from_root<P: AsRef<Path>>(root: P) -> Result<Self> {
+        let root = root.as_ref();
+        let mut policies: Vec<_> = fs::read_dir(root.join("sys/devices/system/cpu/cpufreq"))?
             .filter_map(|d| Some(d.ok()?.path()))
             .filter(|p| p.is_dir())
             .filter(|p| {
                 p.file_name()
-                    .and_then(OsStr::to_str)
-                    .unwrap()
-                    .contains("policy")
+                    .and_then_likely(OsStr::to_str)
+                    .is_some_and(|n| n.contains("policy"))
+            })
+            .filter_map(|p| {
+                Policy::new(root, &p)
+                    .map_err(|e| warn!("Skip cpufreq policy {}: {e}", p.display()))
+                    .ok()
             })
-            .map(Policy::new)
-            .map(Result::unwrap)
             .collect();
 
-        policies.sort_unstable();
-        if policies.len() > 2 {
-            policies[0].little = true;
+        if policies.is_empty() {
+            return Err(Error::Other("No usable cpufreq policy found").into());
+        }
+
+        topology::sort_and_mark(&mut policies);
+        for line in topology::describe(&policies).lines() {
+            info!("Cpu topology: {line}");
         }
 
+        Policy::detect_force_bound(root, &mut policies);
+        Policy::detect_cooling(root, &mut policies);
+
         let mut freqs: Vec<_> = policies
             .iter()
             .flat_map(|p| p.freqs.iter().copied())
@@ -61,53 +100,92 @@ impl CpuCommon {
         let fas_freq = Cell::new(last_freq);
 
         Ok(Self {
+            root: root.to_path_buf(),
             freqs,
             fas_freq,
             policies,
+            usage: UsageTracker::default(),
+            verifier: Verifier::default(),
+            in_game: Cell::new(false),
+            ceiling: Cell::new(1.0),
+            hotplug_timer: Cell::new(Instant::now()),
         })
     }
 
-    fn reset_freq(&self) {
+    // 当前fas频率在频率表中的相对位置(0.0 ~ 1.0)
+    pub fn level(&self) -> f64 {
+        let first_freq = self.freqs[0];
+        let last_freq = self.freqs.last().copied().unwrap();
+
+        if last_freq == first_freq {
+            return 1.0;
+        }
+
+        (self.fas_freq.get() - first_freq) as f64 / (last_freq - first_freq) as f64
+    }
+
+    // 温控上限对应的最高fas频率
+    fn max_freq(&self) -> Freq {
+        let first_freq = self.freqs[0];
         let last_freq = self.freqs.last().copied().unwrap();
-        self.fas_freq.set(last_freq);
+        let cap = first_freq + ((last_freq - first_freq) as f64 * self.ceiling.get()) as Freq;
+
+        self.freqs
+            .iter()
+            .copied()
+            .filter(|f| *f <= cap)
+            .max()
+            .unwrap_or(first_freq)
+    }
+
+    fn reset_freq(&self) {
+        let max_freq = self.max_freq();
+        self.fas_freq.set(max_freq);
 
-        for policy in &self.policies {
-            let _ = policy.set_fas_freq(last_freq);
+        for policy in self.online_policies() {
+            let _ = policy.set_fas_freq(max_freq);
         }
     }
 }
 
 impl PerformanceController for CpuCommon {
-    fn limit(&self, _m: Mode, _c: &Config) -> FrameworkResult<()> {
+    fn limit(&self, m: Mode, c: &Config) -> FrameworkResult<()> {
+        self.check_hotplug(m, c);
+        self.verify(c);
+
         let current_freq = self.fas_freq.get();
         let limited_freq = current_freq.saturating_sub(50000).max(self.freqs[0]);
         self.fas_freq.set(limited_freq);
 
-        for policy in &self.policies {
+        for policy in self.online_policies() {
             let _ = policy.set_fas_freq(limited_freq);
         }
 
         Ok(())
     }
 
-    fn release(&self, _m: Mode, _c: &Config) -> FrameworkResult<()> {
+    fn release(&self, m: Mode, c: &Config) -> FrameworkResult<()> {
+        self.check_hotplug(m, c);
+        self.verify(c);
+
         let current_freq = self.fas_freq.get();
-        let released_freq = current_freq
-            .saturating_add(50000)
-            .min(self.freqs.last().copied().unwrap());
+        let released_freq = current_freq.saturating_add(50000).min(self.max_freq());
         self.fas_freq.set(released_freq);
 
-        for policy in &self.policies {
+        for policy in self.online_policies() {
             let _ = policy.set_fas_freq(released_freq);
         }
 
         Ok(())
     }
 
-    fn release_max(&self, _m: Mode, _c: &Config) -> FrameworkResult<()> {
-        let max_freq = self.freqs.last().copied().unwrap();
+    fn release_max(&self, m: Mode, c: &Config) -> FrameworkResult<()> {
+        self.check_hotplug(m, c);
+        self.verify(c);
+
+        let max_freq = self.max_freq();
 
-        for policy in &self.policies {
+        for policy in self.online_policies() {
             let _ = policy.set_fas_freq(max_freq);
         }
 
@@ -115,22 +193,83 @@ impl PerformanceController for CpuCommon {
     }
 
     fn init_game(&self, m: Mode, c: &Config) -> FrameworkResult<()> {
+        self.check_hotplug(m, c);
+        self.in_game.set(true);
         self.reset_freq();
 
-        for policy in &self.policies {
+        for policy in self.online_policies() {
             let _ = policy.init_game(m, c);
         }
 
         Ok(())
     }
 
-    fn init_default(&self, _m: Mode, _c: &Config) -> FrameworkResult<()> {
+    fn init_default(&self, m: Mode, c: &Config) -> FrameworkResult<()> {
+        self.check_hotplug(m, c);
+        self.in_game.set(false);
         self.reset_freq();
 
-        for policy in &self.policies {
+        for policy in self.online_policies() {
             let _ = policy.init_default();
         }
 
         Ok(())
     }
+
+    fn set_ceiling(&self, ceiling: f64) {
+        self.ceiling.set(ceiling.clamp(0.0, 1.0));
+
+        let max_freq = self.max_freq();
+        if self.in_game.get() && self.fas_freq.get() > max_freq {
+            self.fas_freq.set(max_freq);
+
+            for policy in self.online_policies() {
+                let _ = policy.set_fas_freq(max_freq);
+            }
+        }
+    }
+
+    fn status(&self) -> Vec<(String, String)> {
+        vec![
+            ("cpu_topology".into(), topology::describe(&self.policies)),
+            (
+                "cpu_contention".into(),
+                self.verifier.contention().to_string(),
+            ),
+        ]
+    }
+
+    fn usage(&self) -> Option<f64> {
+        self.usage
+            .cluster_usages(&self.policies)?
+            .into_iter()
+            .reduce(f64::max)
+    }
+}
+
+// 测试用的两集群拓扑: policy0(cpu0-3)与policy4(cpu4-7)
+#[cfg(test)]
+pub fn two_clusters(name: &str) -> crate::misc::Fixture {
+    let fixture = crate::misc::Fixture::new(name);
+
+    for (num, cpus, freqs, capacity) in [
+        (0, [0, 1, 2, 3], "300000 1000000 1800000", 400),
+        (4, [4, 5, 6, 7], "500000 1500000 2400000", 1024),
+    ] {
+        let dir = format!("sys/devices/system/cpu/cpufreq/policy{num}");
+        let related: Vec<_> = cpus.iter().map(ToString::to_string).collect();
+
+        fixture.write(format!("{dir}/related_cpus"), related.join(" "));
+        fixture.write(format!("{dir}/scaling_available_frequencies"), freqs);
+        fixture.write(format!("{dir}/scaling_governor"), "schedutil");
+
+        for cpu in cpus {
+            fixture.write(
+                format!("sys/devices/system/cpu/cpu{cpu}/cpu_capacity"),
+                capacity.to_string(),
+            );
+        }
+    }
+
+    fixture
 }
@@ -0,0 +1,62 @@
+/* Copyright 2023 shadow3aaa@gitbub.com
+*
+*  Licensed under the Apache License, Version 2.0 (the "License");
+*  you may not use this file except in compliance with the License.
+*  You may obtain a copy of the License at
+*
+*      http://www.apache.org/licenses/LICENSE-2.0
+*
+*  Unless required by applicable law or agreed to in writing, software
+*  distributed under the License is distributed on an "AS IS" BASIS,
+*  WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+*  See the License for the specific language governing permissions and
+*  limitations under the License. */
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+use anyhow::Result;
+
+use super::{Bounder, Freq, Policy};
+
+const PM_QOS: &str = "sys/power";
+
+// exynos的pm qos限制是全局的, 内核会按频率表映射到各个集群, 所以只跟随最大的集群
+#[derive(Debug, PartialEq, Eq)]
+pub struct ExynosPmQos {
+    min_limit: PathBuf,
+    max_limit: PathBuf,
+    big_policy: u8,
+}
+
+impl ExynosPmQos {
+    pub fn detect(root: &Path, policies: &[Policy]) -> Option<Self> {
+        let path = root.join(PM_QOS);
+        let min_limit = path.join("cpufreq_min_limit");
+        let max_limit = path.join("cpufreq_max_limit");
+        let big_policy = policies.iter().map(|p| p.num).max()?;
+
+        if min_limit.exists() && max_limit.exists() {
+            Some(Self {
+                min_limit,
+                max_limit,
+                big_policy,
+            })
+        } else {
+            None
+        }
+    }
+}
+
+impl Bounder for ExynosPmQos {
+    fn force_freq(&self, policy: &Policy, l: Freq, r: Freq) -> Result<()> {
+        if policy.num != self.big_policy {
+            return Ok(());
+        }
+
+        fs::write(&self.max_limit, r.to_string())?;
+        fs::write(&self.min_limit, l.to_string())?;
+        Ok(())
+    }
+}
@@ -0,0 +1,186 @@
+/* Copyright 2023 shadow3aaa@gitbub.com
+*
+*  Licensed under the Apache License, Version 2.0 (the "License");
+*  you may not use this file except in compliance with the License.
+*  You may obtain a copy of the License at
+*
+*      http://www.apache.org/licenses/LICENSE-2.0
+*
+*  Unless required by applicable law or agreed to in writing, software
+*  distributed under the License is distributed on an "AS IS" BASIS,
+*  WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+*  See the License for the specific language governing permissions and
+*  limitations under the License. */
+mod exynos;
+mod msm;
+mod mtk;
+
+use std::{fmt::Debug, path::Path, sync::Arc};
+
+use anyhow::Result;
+use log::info;
+
+use super::{Freq, Policy};
+use exynos::ExynosPmQos;
+use msm::MsmPerformance;
+use mtk::{CpuDvfs, Ppm};
+
+// 厂商提供的强制频率接口, 会覆盖scaling_max_freq/scaling_min_freq
+// 目前支持mtk cpudvfs / ppm, 高通msm_performance和exynos pm qos
+// 紫光展锐(Unisoc)还没有经过设备确认的节点布局, 暂不支持, 只使用scaling_max_freq/scaling_min_freq
+pub trait Bounder: Debug + Send + Sync {
+    fn force_freq(&self, policy: &Policy, l: Freq, r: Freq) -> Result<()>;
+}
+
+pub fn detect<P: AsRef<Path>>(root: P, policies: &[Policy]) -> Vec<Arc<dyn Bounder>> {
+    let root = root.as_ref();
+    let mut bounders: Vec<Arc<dyn Bounder>> = Vec::new();
+
+    if let Some(b) = CpuDvfs::detect(root) {
+        bounders.push(Arc::new(b));
+    }
+
+    if let Some(b) = Ppm::detect(root) {
+        bounders.push(Arc::new(b));
+    }
+
+    if let Some(b) = MsmPerformance::detect(root) {
+        bounders.push(Arc::new(b));
+    }
+
+    if let Some(b) = ExynosPmQos::detect(root, policies) {
+        bounders.push(Arc::new(b));
+    }
+
+    for bounder in &bounders {
+        info!("Found force bound interface: {bounder:?}");
+    }
+
+    bounders
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::misc::Fixture;
+
+    fn policy(fixture: &Fixture, num: u8, cpus: &str, cluster: usize) -> Policy {
+        let dir = format!("sys/devices/system/cpu/cpufreq/policy{num}");
+        fixture.write(format!("{dir}/related_cpus"), cpus);
+        fixture.write(
+            format!("{dir}/scaling_available_frequencies"),
+            "500000 1000000 2000000\n",
+        );
+
+        let mut policy = Policy::new(fixture.path(), fixture.path().join(dir)).unwrap();
+        policy.cluster = cluster;
+        policy
+    }
+
+    #[test]
+    fn detect_none() {
+        let fixture = Fixture::new("bound-none");
+        let policies = [policy(&fixture, 0, "0 1 2 3", 0)];
+
+        assert!(detect(fixture.path(), &policies).is_empty());
+    }
+
+    #[test]
+    fn cpudvfs() {
+        let fixture = Fixture::new("bound-cpudvfs");
+        let policy = policy(&fixture, 4, "4 5 6", 1);
+
+        assert_eq!(CpuDvfs::detect(fixture.path()), None);
+        fixture.write("proc/cpudvfs/cpufreq_debug", "");
+        let bounder = CpuDvfs::detect(fixture.path()).unwrap();
+
+        bounder.force_freq(&policy, 500_000, 1_000_000).unwrap();
+        assert_eq!(
+            fixture.read("proc/cpudvfs/cpufreq_debug"),
+            "4 500000 1000000"
+        );
+    }
+
+    #[test]
+    fn ppm() {
+        let fixture = Fixture::new("bound-ppm");
+        let policy = policy(&fixture, 4, "4 5 6", 1);
+
+        fixture.write("proc/ppm/policy/hard_userlimit_min_cpu_freq", "");
+        assert_eq!(Ppm::detect(fixture.path()), None);
+        fixture.write("proc/ppm/policy/hard_userlimit_max_cpu_freq", "");
+        let bounder = Ppm::detect(fixture.path()).unwrap();
+
+        bounder.force_freq(&policy, 500_000, 2_000_000).unwrap();
+        assert_eq!(
+            fixture.read("proc/ppm/policy/hard_userlimit_max_cpu_freq"),
+            "1 2000000"
+        );
+        assert_eq!(
+            fixture.read("proc/ppm/policy/hard_userlimit_min_cpu_freq"),
+            "1 500000"
+        );
+    }
+
+    #[test]
+    fn msm_performance() {
+        let fixture = Fixture::new("bound-msm");
+        let policy = policy(&fixture, 4, "4 5 6", 1);
+        let params = "sys/module/msm_performance/parameters";
+
+        fixture.write(format!("{params}/cpu_max_freq"), "");
+        assert_eq!(MsmPerformance::detect(fixture.path()), None);
+        fixture.write(format!("{params}/cpu_min_freq"), "");
+        let bounder = MsmPerformance::detect(fixture.path()).unwrap();
+
+        bounder.force_freq(&policy, 500_000, 1_000_000).unwrap();
+        assert_eq!(
+            fixture.read(format!("{params}/cpu_max_freq")),
+            "4:1000000 5:1000000 6:1000000"
+        );
+        assert_eq!(
+            fixture.read(format!("{params}/cpu_min_freq")),
+            "4:500000 5:500000 6:500000"
+        );
+    }
+
+    #[test]
+    fn exynos_pm_qos() {
+        let fixture = Fixture::new("bound-exynos");
+        let policies = [
+            policy(&fixture, 0, "0 1 2 3", 0),
+            policy(&fixture, 4, "4 5 6", 1),
+        ];
+
+        fixture.write("sys/power/cpufreq_min_limit", "-1");
+        fixture.write("sys/power/cpufreq_max_limit", "-1");
+        assert_eq!(ExynosPmQos::detect(fixture.path(), &[]), None);
+        let bounder = ExynosPmQos::detect(fixture.path(), &policies).unwrap();
+
+        // 只跟随最大的集群, 小核的写入被忽略
+        bounder.force_freq(&policies[0], 500_000, 500_000).unwrap();
+        assert_eq!(fixture.read("sys/power/cpufreq_max_limit"), "-1");
+        assert_eq!(fixture.read("sys/power/cpufreq_min_limit"), "-1");
+
+        bounder
+            .force_freq(&policies[1], 1_000_000, 2_000_000)
+            .unwrap();
+        assert_eq!(fixture.read("sys/power/cpufreq_max_limit"), "2000000");
+        assert_eq!(fixture.read("sys/power/cpufreq_min_limit"), "1000000");
+    }
+
+    #[test]
+    fn detect_all() {
+        let fixture = Fixture::new("bound-all");
+        let policies = [policy(&fixture, 0, "0 1 2 3", 0)];
+
+        fixture.write("proc/cpudvfs/cpufreq_debug", "");
+        fixture.write("sys/power/cpufreq_min_limit", "");
+        fixture.write("sys/power/cpufreq_max_limit", "");
+
+        let bounders = detect(fixture.path(), &policies);
+        assert_eq!(bounders.len(), 2);
+        assert!(format!("{:?}", bounders[0]).starts_with("CpuDvfs"));
+        assert!(format!("{:?}", bounders[1]).starts_with("ExynosPmQos"));
+    }
+}
@@ -0,0 +1,61 @@
+/* Copyright 2023 shadow3aaa@gitbub.com
+*
+*  Licensed under the Apache License, Version 2.0 (the "License");
+*  you may not use this file except in compliance with the License.
+*  You may obtain a copy of the License at
+*
+*      http://www.apache.org/licenses/LICENSE-2.0
+*
+*  Unless required by applicable law or agreed to in writing, software
+*  distributed under the License is distributed on an "AS IS" BASIS,
+*  WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+*  See the License for the specific language governing permissions and
+*  limitations under the License. */
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+use anyhow::Result;
+
+use super::{Bounder, Freq, Policy};
+
+const MSM_PERFORMANCE: &str = "sys/module/msm_performance/parameters";
+
+// 格式: "cpu:freq cpu:freq ..."
+#[derive(Debug, PartialEq, Eq)]
+pub struct MsmPerformance {
+    min_freq: PathBuf,
+    max_freq: PathBuf,
+}
+
+impl MsmPerformance {
+    pub fn detect(root: &Path) -> Option<Self> {
+        let path = root.join(MSM_PERFORMANCE);
+        let min_freq = path.join("cpu_min_freq");
+        let max_freq = path.join("cpu_max_freq");
+
+        if min_freq.exists() && max_freq.exists() {
+            Some(Self { min_freq, max_freq })
+        } else {
+            None
+        }
+    }
+
+    fn format(policy: &Policy, f: Freq) -> String {
+        policy
+            .cpus
+            .iter()
+            .map(|cpu| format!("{cpu}:{f}"))
+            .collect::<Vec<_>>()
+            .join(" ")
+    }
+}
+
+impl Bounder for MsmPerformance {
+    fn force_freq(&self, policy: &Policy, l: Freq, r: Freq) -> Result<()> {
+        fs::write(&self.max_freq, Self::format(policy, r))?;
+        fs::write(&self.min_freq, Self::format(policy, l))?;
+        Ok(())
+    }
+}
@@ -0,0 +1,81 @@
+/* Copyright 2023 shadow3aaa@gitbub.com
+*
+*  Licensed under the Apache License, Version 2.0 (the "License");
+*  you may not use this file except in compliance with the License.
+*  You may obtain a copy of the License at
+*
+*      http://www.apache.org/licenses/LICENSE-2.0
+*
+*  Unless required by applicable law or agreed to in writing, software
+*  distributed under the License is distributed on an "AS IS" BASIS,
+*  WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+*  See the License for the specific language governing permissions and
+*  limitations under the License. */
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+use anyhow::Result;
+
+use super::{Bounder, Freq, Policy};
+
+const CPUFREQ_DEBUG: &str = "proc/cpudvfs/cpufreq_debug";
+const PPM_POLICY: &str = "proc/ppm/policy";
+
+#[derive(Debug, PartialEq, Eq)]
+pub struct CpuDvfs {
+    freq_debug: PathBuf,
+}
+
+impl CpuDvfs {
+    pub fn detect(root: &Path) -> Option<Self> {
+        let path = root.join(CPUFREQ_DEBUG);
+
+        if path.exists() {
+            Some(Self { freq_debug: path })
+        } else {
+            None
+        }
+    }
+}
+
+impl Bounder for CpuDvfs {
+    fn force_freq(&self, policy: &Policy, l: Freq, r: Freq) -> Result<()> {
+        let message = format!("{} {l} {r}", policy.num);
+        fs::write(&self.freq_debug, message)?;
+        Ok(())
+    }
+}
+
+// ppm按集群序号而不是policy号设置用户硬限制
+#[derive(Debug, PartialEq, Eq)]
+pub struct Ppm {
+    min_limit: PathBuf,
+    max_limit: PathBuf,
+}
+
+impl Ppm {
+    pub fn detect(root: &Path) -> Option<Self> {
+        let path = root.join(PPM_POLICY);
+        let min_limit = path.join("hard_userlimit_min_cpu_freq");
+        let max_limit = path.join("hard_userlimit_max_cpu_freq");
+
+        if min_limit.exists() && max_limit.exists() {
+            Some(Self {
+                min_limit,
+                max_limit,
+            })
+        } else {
+            None
+        }
+    }
+}
+
+impl Bounder for Ppm {
+    fn force_freq(&self, policy: &Policy, l: Freq, r: Freq) -> Result<()> {
+        fs::write(&self.max_limit, format!("{} {r}", policy.cluster))?;
+        fs::write(&self.min_limit, format!("{} {l}", policy.cluster))?;
+        Ok(())
+    }
+}
@@ -16,10 +16,10 @@ mod utils;
 
 use std::{
     cell::{Cell, RefCell},
-    cmp::Ordering,
     ffi::OsStr,
     fs,
     path::{Path, PathBuf},
+    sync::Arc,
 };
 
 use anyhow::Result;
@@ -29,66 +29,187 @@ use super::Freq;
 use crate::{error::Error, framework::prelude::*};
 use force_bound::Bounder;
 
-#[derive(Debug, PartialEq, Eq)]
+#[derive(Debug)]
 pub struct Policy {
     pub little: bool,
     pub num: u8,
+    pub cluster: usize,
+    pub capacity: usize,
     pub path: PathBuf,
+    pub cpus: Vec<usize>,
     pub freqs: Vec<Freq>,
+    pub online: Cell<bool>,
+    pub expected: Cell<Option<(Freq, Freq)>>, // 期望生效的(min, max)
+    cooling: Option<PathBuf>,
     fas_boost: Cell<bool>,
     gov_snapshot: RefCell<Option<String>>,
-    force_bound: Option<Bounder>,
-}
-
-impl Ord for Policy {
-    fn cmp(&self, other: &Self) -> Ordering {
-        self.num.cmp(&other.num)
-    }
-}
-
-impl PartialOrd for Policy {
-    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
-        Some(self.cmp(other))
-    }
+    force_bound: Vec<Arc<dyn Bounder>>,
 }
 
 impl Policy {
-    pub fn new<P: AsRef<Path>>(p: P) -> Result<Self> {
+    pub fn new<R: AsRef<Path>, P: AsRef<Path>>(root: R, p: P) -> Result<Self> {
+        let root = root.as_ref();
         let path = p.as_ref();
 
-        let mut freqs: Vec<Freq> = fs::read_to_string(path.join("scaling_available_frequencies"))?
-            .split_whitespace()
-            .map(|s| s.parse().unwrap())
-            .collect();
-        freqs.sort_unstable();
         let num = path
             .file_name()
             .and_then_likely(OsStr::to_str)
             .and_then_likely(|p| p.replace("policy", "").trim().parse().ok())
             .ok_or(Error::Other("Failed to parse cpufreq policy num"))?;
+        let cpus: Vec<usize> = fs::read_to_string(path.join("related_cpus"))
+            .or_else(|_| fs::read_to_string(path.join("affected_cpus")))?
+            .split_whitespace()
+            .filter_map(|c| c.parse().ok())
+            .collect();
 
-        let force_bound = Bounder::new();
+        let freqs = Self::read_freqs(path)?;
+        let capacity = Self::read_capacity(root, path, &cpus, &freqs);
 
-        Ok(Self {
+        let policy = Self {
             little: false,
             num,
+            cluster: 0,
+            capacity,
             path: path.to_path_buf(),
+            cpus,
             freqs,
+            online: Cell::new(true),
+            expected: Cell::new(None),
+            cooling: None,
             fas_boost: Cell::new(false),
             gov_snapshot: RefCell::new(None),
-            force_bound,
-        })
+            force_bound: Vec::new(),
+        };
+        policy.online.set(policy.read_online(root));
+
+        Ok(policy)
     }
 
-    pub fn init_default(&self) -> Result<()> {
-        if let Some(ref bounder) = self.force_bound {
-            bounder.force_freq(
-                self.num,
-                self.freqs.first().copied().unwrap(),
-                self.freqs.last().copied().unwrap(),
-            )?;
+    // 优先使用scaling_available_frequencies, 部分内核没有此节点时从time_in_state获取频率表
+    fn read_freqs(path: &Path) -> Result<Vec<Freq>> {
+        let parse_table = |table: String, column: usize| -> Vec<Freq> {
+            table
+                .lines()
+                .flat_map(|l| l.split_whitespace().step_by(column))
+                .filter_map(|f| f.parse().ok())
+                .collect()
+        };
+
+        let mut freqs = fs::read_to_string(path.join("scaling_available_frequencies"))
+            .map(|t| parse_table(t, 1))
+            .unwrap_or_default();
+
+        if freqs.is_empty() {
+            freqs = fs::read_to_string(path.join("stats/time_in_state"))
+                .map(|t| parse_table(t, 2))
+                .unwrap_or_default();
         }
 
+        if freqs.is_empty() {
+            freqs = ["cpuinfo_min_freq", "cpuinfo_max_freq"]
+                .iter()
+                .filter_map(|n| fs::read_to_string(path.join(n)).ok())
+                .filter_map(|f| f.trim().parse().ok())
+                .collect();
+        }
+
+        freqs.sort_unstable();
+        freqs.dedup();
+
+        if freqs.is_empty() {
+            Err(Error::Other("Failed to read cpufreq policy frequency table").into())
+        } else {
+            Ok(freqs)
+        }
+    }
+
+    // cpu_capacity不存在时退回cpuinfo_max_freq作为集群性能的度量
+    fn read_capacity(root: &Path, path: &Path, cpus: &[usize], freqs: &[Freq]) -> usize {
+        cpus.first()
+            .and_then_likely(|cpu| {
+                fs::read_to_string(
+                    root.join(format!("sys/devices/system/cpu/cpu{cpu}/cpu_capacity")),
+                )
+                .ok()
+            })
+            .or_else(|| fs::read_to_string(path.join("cpuinfo_max_freq")).ok())
+            .and_then_likely(|c| c.trim().parse().ok())
+            .unwrap_or_else(|| freqs.last().copied().unwrap_or_default())
+    }
+
+    pub fn detect_force_bound(root: &Path, policies: &mut [Self]) {
+        let bounders = force_bound::detect(root, policies);
+
+        for policy in policies {
+            policy.force_bound.clone_from(&bounders);
+        }
+    }
+
+    // 新内核的cpufreq cooling device名为cpufreq-cpuN, 旧内核为thermal-cpufreq-N, N按policy注册顺序递增
+    pub fn detect_cooling(root: &Path, policies: &mut [Self]) {
+        let Ok(devices) = fs::read_dir(root.join("sys/class/thermal")) else {
+            return;
+        };
+
+        let devices: Vec<_> = devices
+            .filter_map(|d| {
+                let path = d.ok()?.path();
+                let name = fs::read_to_string(path.join("type")).ok()?;
+                Some((name.trim().to_string(), path.join("cur_state")))
+            })
+            .collect();
+
+        let mut nums: Vec<_> = policies.iter().map(|p| p.num).collect();
+        nums.sort_unstable();
+
+        for policy in policies {
+            let id = nums.iter().position(|n| *n == policy.num).unwrap();
+
+            policy.cooling = devices
+                .iter()
+                .find(|(name, _)| {
+                    policy
+                        .cpus
+                        .iter()
+                        .any(|cpu| *name == format!("cpufreq-cpu{cpu}"))
+                        || *name == format!("thermal-cpufreq-{id}")
+                })
+                .map(|(_, state)| state.clone());
+        }
+    }
+
+    // 温控状态n表示频率表从高到低第n项为上限
+    pub fn thermal_cap(&self) -> Option<Freq> {
+        let state: usize = fs::read_to_string(self.cooling.as_ref()?)
+            .ok()?
+            .trim()
+            .parse()
+            .ok()?;
+
+        self.freqs
+            .iter()
+            .rev()
+            .nth(state)
+            .or_else(|| self.freqs.first())
+            .copied()
+    }
+
+    fn force_freq(&self, l: Freq, r: Freq) -> Result<()> {
+        for bounder in &self.force_bound {
+            bounder.force_freq(self, l, r)?;
+        }
+
+        Ok(())
+    }
+
+    pub fn init_default(&self) -> Result<()> {
+        self.expected.set(None);
+
+        self.force_freq(
+            self.freqs.first().copied().unwrap(),
+            self.freqs.last().copied().unwrap(),
+        )?;
+
         self.unlock_min_freq(self.freqs[0])?;
         self.unlock_max_freq(self.freqs.last().copied().unwrap())?;
         self.reset_gov()
@@ -102,26 +223,33 @@ impl Policy {
     }
 
     pub fn set_fas_freq(&self, f: Freq) -> Result<()> {
+        // fas频率来自所有集群的频率表, 超出本集群范围的部分内核也会截断
+        let f = f.clamp(
+            self.freqs.first().copied().unwrap(),
+            self.freqs.last().copied().unwrap(),
+        );
+
         if self.fas_boost.get() {
             if self.little {
+                self.expected.set(None);
                 return Ok(());
             }
 
-            self.lock_min_freq(f)?;
             let last_freq = self.freqs.last().copied().unwrap();
+            self.expected.set(Some((f, last_freq)));
+
+            self.lock_min_freq(f)?;
             self.lock_max_freq(last_freq)?;
 
-            if let Some(ref bounder) = self.force_bound {
-                bounder.force_freq(self.num, f, last_freq)?;
-            }
+            self.force_freq(f, last_freq)?;
         } else {
-            self.lock_max_freq(f)?;
             let first_freq = self.freqs.first().copied().unwrap();
+            self.expected.set(Some((first_freq, f)));
+
+            self.lock_max_freq(f)?;
             self.lock_min_freq(first_freq)?;
 
-            if let Some(ref bounder) = self.force_bound {
-                bounder.force_freq(self.num, first_freq, f)?;
-            }
+            self.force_freq(first_freq, f)?;
         }
 
         Ok(())
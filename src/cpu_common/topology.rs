@@ -0,0 +1,178 @@
+/* Copyright 2023 shadow3aaa@gitbub.com
+*
+*  Licensed under the Apache License, Version 2.0 (the "License");
+*  you may not use this file except in compliance with the License.
+*  You may obtain a copy of the License at
+*
+*      http://www.apache.org/licenses/LICENSE-2.0
+*
+*  Unless required by applicable law or agreed to in writing, software
+*  distributed under the License is distributed on an "AS IS" BASIS,
+*  WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+*  See the License for the specific language governing permissions and
+*  limitations under the License. */
+use std::fmt::Write;
+
+use super::policy::Policy;
+
+// 按集群性能排序, 只有性能最低且低于其它集群的集群才视为小核
+pub fn sort_and_mark(policies: &mut [Policy]) {
+    policies.sort_by_key(|p| (p.capacity, p.freqs.last().copied(), p.num));
+
+    let min_capacity = policies.iter().map(|p| p.capacity).min();
+    let max_capacity = policies.iter().map(|p| p.capacity).max();
+
+    for (cluster, policy) in policies.iter_mut().enumerate() {
+        policy.cluster = cluster;
+        policy.little = min_capacity < max_capacity && Some(policy.capacity) == min_capacity;
+    }
+}
+
+pub fn describe(policies: &[Policy]) -> String {
+    policies.iter().fold(String::new(), |mut result, policy| {
+        let cpus: Vec<_> = policy.cpus.iter().map(ToString::to_string).collect();
+
+        let _ = writeln!(
+            result,
+            "cluster{}: policy{} cpus: [{}] capacity: {} freqs: {}-{} little: {} online: {}",
+            policy.cluster,
+            policy.num,
+            cpus.join(" "),
+            policy.capacity,
+            policy.freqs[0],
+            policy.freqs.last().copied().unwrap(),
+            policy.little,
+            policy.online.get(),
+        );
+
+        result
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use super::*;
+    use crate::{cpu_common::two_clusters, misc::Fixture};
+
+    const CPUFREQ: &str = "sys/devices/system/cpu/cpufreq";
+
+    fn policies(fixture: &Fixture) -> Vec<Policy> {
+        let mut policies: Vec<_> = fs::read_dir(fixture.path().join(CPUFREQ))
+            .unwrap()
+            .map(|d| Policy::new(fixture.path(), d.unwrap().path()).unwrap())
+            .collect();
+        sort_and_mark(&mut policies);
+
+        policies
+    }
+
+    fn summary(policies: &[Policy]) -> Vec<(u8, usize, bool)> {
+        policies
+            .iter()
+            .map(|p| (p.num, p.cluster, p.little))
+            .collect()
+    }
+
+    fn write_policy(fixture: &Fixture, num: u8, cpus: &[usize], capacity: Option<usize>) {
+        let dir = format!("{CPUFREQ}/policy{num}");
+        let related: Vec<_> = cpus.iter().map(ToString::to_string).collect();
+
+        fixture.write(format!("{dir}/related_cpus"), related.join(" "));
+        fixture.write(
+            format!("{dir}/scaling_available_frequencies"),
+            "500000 1000000 2000000",
+        );
+
+        if let Some(capacity) = capacity {
+            for cpu in cpus {
+                fixture.write(
+                    format!("sys/devices/system/cpu/cpu{cpu}/cpu_capacity"),
+                    capacity.to_string(),
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn little_cluster() {
+        let fixture = two_clusters("topology-two");
+        let policies = policies(&fixture);
+
+        assert_eq!(summary(&policies), [(0, 0, true), (4, 1, false)]);
+        assert_eq!(policies[0].cpus, [0, 1, 2, 3]);
+        assert_eq!(policies[1].capacity, 1024);
+        assert_eq!(
+            describe(&policies).lines().next(),
+            Some(
+                "cluster0: policy0 cpus: [0 1 2 3] capacity: 400 freqs: 300000-1800000 little: true online: true"
+            )
+        );
+    }
+
+    #[test]
+    fn numbering_not_by_capacity() {
+        let fixture = Fixture::new("topology-numbering");
+        write_policy(&fixture, 0, &[0, 1], Some(1024));
+        write_policy(&fixture, 2, &[2, 3, 4, 5], Some(300));
+        write_policy(&fixture, 6, &[6, 7], Some(700));
+
+        assert_eq!(
+            summary(&policies(&fixture)),
+            [(2, 0, true), (6, 1, false), (0, 2, false)]
+        );
+    }
+
+    #[test]
+    fn no_little_cluster() {
+        // 所有集群性能相同时没有小核
+        let fixture = Fixture::new("topology-same");
+        write_policy(&fixture, 4, &[4, 5, 6, 7], Some(1024));
+        write_policy(&fixture, 0, &[0, 1, 2, 3], Some(1024));
+
+        assert_eq!(summary(&policies(&fixture)), [(0, 0, false), (4, 1, false)]);
+
+        // 没有cpu_capacity时按cpuinfo_max_freq比较
+        let fixture = Fixture::new("topology-max-freq");
+        write_policy(&fixture, 0, &[0, 1, 2, 3], None);
+        write_policy(&fixture, 4, &[4, 5, 6, 7], None);
+        fixture.write(format!("{CPUFREQ}/policy0/cpuinfo_max_freq"), "2800000");
+        fixture.write(format!("{CPUFREQ}/policy4/cpuinfo_max_freq"), "2000000");
+
+        assert_eq!(summary(&policies(&fixture)), [(4, 0, true), (0, 1, false)]);
+    }
+
+    #[test]
+    fn time_in_state_freqs() {
+        let fixture = two_clusters("topology-time-in-state");
+        let little = format!("{CPUFREQ}/policy0");
+        fs::remove_file(
+            fixture
+                .path()
+                .join(&little)
+                .join("scaling_available_frequencies"),
+        )
+        .unwrap();
+        fixture.write(
+            format!("{little}/stats/time_in_state"),
+            "1800000 10\n300000 500\n1000000 0\n1000000 0\n",
+        );
+
+        let policies = policies(&fixture);
+        assert_eq!(policies[0].num, 0);
+        assert_eq!(policies[0].freqs, [300_000, 1_000_000, 1_800_000]);
+        assert!(policies[0].little);
+
+        // 两者都没有时只能使用cpuinfo_min_freq / cpuinfo_max_freq
+        fs::remove_dir_all(fixture.path().join(&little).join("stats")).unwrap();
+        fixture.write(format!("{little}/cpuinfo_min_freq"), "300000");
+        fixture.write(format!("{little}/cpuinfo_max_freq"), "1800000");
+        let policy = Policy::new(fixture.path(), fixture.path().join(&little)).unwrap();
+        assert_eq!(policy.freqs, [300_000, 1_800_000]);
+
+        fs::remove_file(fixture.path().join(&little).join("cpuinfo_min_freq")).unwrap();
+        fs::remove_file(fixture.path().join(&little).join("cpuinfo_max_freq")).unwrap();
+        assert!(Policy::new(fixture.path(), fixture.path().join(&little)).is_err());
+    }
+}
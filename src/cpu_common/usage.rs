@@ -0,0 +1,98 @@
+/* Copyright 2023 shadow3aaa@gitbub.com
+*
+*  Licensed under the Apache License, Version 2.0 (the "License");
+*  you may not use this file except in compliance with the License.
+*  You may obtain a copy of the License at
+*
+*      http://www.apache.org/licenses/LICENSE-2.0
+*
+*  Unless required by applicable law or agreed to in writing, software
+*  distributed under the License is distributed on an "AS IS" BASIS,
+*  WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+*  See the License for the specific language governing permissions and
+*  limitations under the License. */
+use std::{
+    cell::RefCell,
+    collections::HashMap,
+    fs,
+    time::{Duration, Instant},
+};
+
+use super::policy::Policy;
+
+// /proc/stat按jiffy(10ms)计数, 采样周期太短时使用率只会是0, 0.5或者1
+const SAMPLE_PERIOD: Duration = Duration::from_millis(200);
+
+type CpuStat = HashMap<usize, (u64, u64)>; // cpu, (busy, total)
+
+#[derive(Debug, Default)]
+pub struct UsageTracker {
+    last_stat: RefCell<Option<(Instant, CpuStat)>>,
+    usages: RefCell<Option<Vec<f64>>>,
+}
+
+impl UsageTracker {
+    // 每个集群中最忙核心的使用率, 至少间隔SAMPLE_PERIOD采样一次, 期间返回上一次的结果
+    pub fn cluster_usages(&self, policies: &[Policy]) -> Option<Vec<f64>> {
+        if self
+            .last_stat
+            .borrow()
+            .as_ref()
+            .is_some_and(|(t, _)| t.elapsed() < SAMPLE_PERIOD)
+        {
+            return self.usages.borrow().clone();
+        }
+
+        let stat = Self::read_stat()?;
+        let last_stat = self.last_stat.replace(Some((Instant::now(), stat.clone())));
+        let (_, last_stat) = last_stat?;
+
+        let usages = policies
+            .iter()
+            .map(|policy| {
+                policy
+                    .cpus
+                    .iter()
+                    .filter_map(|cpu| {
+                        let (busy, total) = stat.get(cpu)?;
+                        let (last_busy, last_total) = last_stat.get(cpu)?;
+
+                        let busy = busy.saturating_sub(*last_busy);
+                        let total = total.saturating_sub(*last_total);
+
+                        if total == 0 {
+                            None
+                        } else {
+                            Some(busy as f64 / total as f64)
+                        }
+                    })
+                    .fold(0.0, f64::max)
+            })
+            .collect::<Vec<_>>();
+
+        *self.usages.borrow_mut() = Some(usages.clone());
+        Some(usages)
+    }
+
+    fn read_stat() -> Option<CpuStat> {
+        let stat = fs::read_to_string("/proc/stat").ok()?;
+
+        let stat = stat
+            .lines()
+            .filter(|l| l.starts_with("cpu") && !l.starts_with("cpu "))
+            .filter_map(|l| {
+                let mut fields = l.split_whitespace();
+                let cpu = fields.next()?.trim_start_matches("cpu").parse().ok()?;
+                let times: Vec<u64> = fields.take(8).filter_map(|t| t.parse().ok()).collect();
+
+                // user nice system idle iowait irq softirq steal
+                let total = times.iter().sum();
+                let idle = times.get(3)? + times.get(4).copied().unwrap_or_default();
+
+                Some((cpu, (total - idle, total)))
+            })
+            .collect();
+
+        Some(stat)
+    }
+}
@@ -0,0 +1,267 @@
+/* Copyright 2023 shadow3aaa@gitbub.com
+*
+*  Licensed under the Apache License, Version 2.0 (the "License");
+*  you may not use this file except in compliance with the License.
+*  You may obtain a copy of the License at
+*
+*      http://www.apache.org/licenses/LICENSE-2.0
+*
+*  Unless required by applicable law or agreed to in writing, software
+*  distributed under the License is distributed on an "AS IS" BASIS,
+*  WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+*  See the License for the specific language governing permissions and
+*  limitations under the License. */
+use std::{
+    cell::Cell,
+    fs,
+    path::Path,
+    time::{Duration, Instant},
+};
+
+use log::{info, warn};
+
+use super::{policy::Policy, CpuCommon, Freq};
+use crate::framework::prelude::*;
+
+const VERIFY_TIME: Duration = Duration::from_secs(1);
+const MAX_BACKOFF: Duration = Duration::from_secs(32);
+
+// 已知会改写cpufreq限制的厂商服务
+const KNOWN_AGENTS: [&str; 12] = [
+    "perfd",
+    "perf-hal",
+    "vendor.qti.hardware.perf",
+    "android.hardware.power",
+    "thermal-engine",
+    "thermald",
+    "mi_thermald",
+    "thermal-hal",
+    "vendor.thermal",
+    "joyose",
+    "oplus_perf",
+    "hans",
+];
+
+#[derive(Debug)]
+pub struct Verifier {
+    contention: Cell<usize>,
+    contended: Cell<bool>,
+    timer: Cell<Instant>,
+    backoff: Cell<Duration>,
+    last_reassert: Cell<Instant>,
+}
+
+impl Default for Verifier {
+    fn default() -> Self {
+        Self {
+            contention: Cell::new(0),
+            contended: Cell::new(false),
+            timer: Cell::new(Instant::now()),
+            backoff: Cell::new(VERIFY_TIME),
+            last_reassert: Cell::new(Instant::now()),
+        }
+    }
+}
+
+impl Verifier {
+    pub const fn contention(&self) -> usize {
+        self.contention.get()
+    }
+}
+
+impl Policy {
+    fn read_back(&self) -> Option<(Freq, Freq)> {
+        let read = |n: &str| -> Option<Freq> {
+            fs::read_to_string(self.path.join(n))
+                .ok()?
+                .trim()
+                .parse()
+                .ok()
+        };
+
+        Some((read("scaling_min_freq")?, read("scaling_max_freq")?))
+    }
+
+    // 内核温控压低的policy->max不算作被覆盖
+    fn expected_effective(&self) -> Option<(Freq, Freq)> {
+        let (min, max) = self.expected.get()?;
+        let max = self.thermal_cap().map_or(max, |cap| max.min(cap));
+
+        Some((min.min(max), max))
+    }
+}
+
+impl CpuCommon {
+    // 回读实际生效的频率限制, 检测是否被其它进程覆盖
+    pub fn verify(&self, c: &Config) {
+        let verifier = &self.verifier;
+
+        if verifier.timer.get().elapsed() < VERIFY_TIME {
+            return;
+        }
+        verifier.timer.set(Instant::now());
+
+        let overridden: Vec<_> = self
+            .online_policies()
+            .filter_map(|p| {
+                let expected = p.expected_effective()?;
+                let effective = p.read_back()?;
+
+                if expected == effective {
+                    None
+                } else {
+                    Some((p, expected, effective))
+                }
+            })
+            .collect();
+
+        if overridden.is_empty() {
+            if verifier.contended.replace(false) {
+                info!("Cpufreq limits are no longer contended");
+            }
+
+            verifier.backoff.set(VERIFY_TIME);
+            return;
+        }
+
+        verifier.contention.set(verifier.contention.get() + 1);
+
+        if !verifier.contended.replace(true) {
+            for (policy, expected, effective) in &overridden {
+                warn!(
+                    "Cpufreq policy{} limits overridden, expected: {expected:?} effective: {effective:?}",
+                    policy.num
+                );
+            }
+
+            let agents = Self::running_agents();
+            if agents.is_empty() {
+                warn!("No known conflicting process found, maybe a kernel driver");
+            } else {
+                warn!("Possible conflicting processes: {}", agents.join(", "));
+            }
+        }
+
+        if !c.global_config().reassert_freq
+            || verifier.last_reassert.get().elapsed() < verifier.backoff.get()
+        {
+            return;
+        }
+
+        verifier.last_reassert.set(Instant::now());
+        verifier
+            .backoff
+            .set((verifier.backoff.get() * 2).min(MAX_BACKOFF));
+
+        for (policy, _, _) in overridden {
+            let _ = policy.set_fas_freq(self.fas_freq.get());
+        }
+    }
+
+    fn running_agents() -> Vec<String> {
+        let Ok(proc) = fs::read_dir("/proc") else {
+            return Vec::new();
+        };
+
+        let mut agents: Vec<_> = proc
+            .filter_map(|d| Some(d.ok()?.path()))
+            .filter_map(|p| {
+                let cmdline = fs::read_to_string(p.join("cmdline")).ok()?;
+                let name = cmdline.split('\0').next()?;
+                let name = Path::new(name).file_name()?.to_str()?;
+
+                KNOWN_AGENTS
+                    .iter()
+                    .any(|a| name.contains(a))
+                    .then(|| name.to_string())
+            })
+            .collect();
+        agents.sort_unstable();
+        agents.dedup();
+
+        agents
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cpu_common::two_clusters;
+
+    const LITTLE: &str = "sys/devices/system/cpu/cpufreq/policy0";
+    const BIG: &str = "sys/devices/system/cpu/cpufreq/policy4";
+
+    fn verify(cpu: &CpuCommon, config: &Config) -> usize {
+        cpu.verifier
+            .timer
+            .set(Instant::now().checked_sub(VERIFY_TIME).unwrap());
+        cpu.verify(config);
+        cpu.verifier.contention()
+    }
+
+    #[test]
+    fn multi_cluster() {
+        let fixture = two_clusters("verify-cluster");
+        let config = Config::new("module/games.toml", "module/games.toml").unwrap();
+        let cpu = CpuCommon::from_root(fixture.path()).unwrap();
+
+        cpu.init_game(Mode::Powersave, &config).unwrap();
+        cpu.limit(Mode::Powersave, &config).unwrap();
+
+        // 2350000超出小核频率表, 内核会截断到1800000
+        assert_eq!(cpu.fas_freq.get(), 2_350_000);
+        assert_eq!(
+            fixture.read(format!("{LITTLE}/scaling_max_freq")),
+            "1800000"
+        );
+        assert_eq!(verify(&cpu, &config), 0);
+
+        fixture.write(format!("{LITTLE}/scaling_max_freq"), "1000000");
+        assert_eq!(verify(&cpu, &config), 1);
+    }
+
+    #[test]
+    fn thermal_cap() {
+        let fixture = two_clusters("verify-thermal");
+        fixture.write("sys/class/thermal/cooling_device0/type", "cpufreq-cpu0\n");
+        fixture.write("sys/class/thermal/cooling_device0/cur_state", "0\n");
+        fixture.write("sys/class/thermal/cooling_device1/type", "cpufreq-cpu4\n");
+        fixture.write("sys/class/thermal/cooling_device1/cur_state", "1\n");
+        let config = Config::new("module/games.toml", "module/games.toml").unwrap();
+        let cpu = CpuCommon::from_root(fixture.path()).unwrap();
+
+        cpu.init_game(Mode::Powersave, &config).unwrap();
+
+        // 温控状态1对应频率表从高到低的第二项
+        fixture.write(format!("{BIG}/scaling_max_freq"), "1500000");
+        assert_eq!(verify(&cpu, &config), 0);
+
+        fixture.write("sys/class/thermal/cooling_device1/cur_state", "0\n");
+        assert_eq!(verify(&cpu, &config), 1);
+    }
+
+    #[test]
+    fn legacy_cooling_device() {
+        let fixture = two_clusters("verify-legacy");
+        fixture.write(
+            "sys/class/thermal/cooling_device0/type",
+            "thermal-cpufreq-0\n",
+        );
+        fixture.write("sys/class/thermal/cooling_device0/cur_state", "0\n");
+        fixture.write(
+            "sys/class/thermal/cooling_device1/type",
+            "thermal-cpufreq-1\n",
+        );
+        fixture.write("sys/class/thermal/cooling_device1/cur_state", "2\n");
+        let config = Config::new("module/games.toml", "module/games.toml").unwrap();
+        let cpu = CpuCommon::from_root(fixture.path()).unwrap();
+
+        cpu.init_game(Mode::Powersave, &config).unwrap();
+
+        let big = cpu.policies.iter().find(|p| p.num == 4).unwrap();
+        assert_eq!(big.thermal_cap(), Some(500_000));
+
+        fixture.write(format!("{BIG}/scaling_max_freq"), "500000");
+        assert_eq!(verify(&cpu, &config), 0);
+    }
+}
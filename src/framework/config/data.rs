@@ -12,28 +12,230 @@
 *  See the License for the specific language governing permissions and
 *  limitations under the License. */
 use serde_derive::Deserialize;
-use toml::Table;
+use toml::{Table, Value};
+
+use super::timing::{TimingConfig, TimingOverride};
+use crate::framework::{
+    error::{Error, Result},
+    node::Mode,
+};
 
 #[derive(Debug, Deserialize, Clone)]
 pub struct ConfigData {
     pub config: Config,
     pub game_list: Table,
+    #[serde(default)]
+    pub thermal: ThermalConfig,
+    #[serde(default)]
+    pub auto_mode: AutoModeConfig,
+    #[serde(default)]
+    pub timing: TimingConfig,
+    #[serde(default)]
+    pub scene: SceneConfig,
+    #[serde(default)]
+    pub window_mode: WindowModeConfig,
     pub powersave: ModeConfig,
     pub balance: ModeConfig,
     pub performance: ModeConfig,
     pub fast: ModeConfig,
 }
 
+impl ConfigData {
+    // 检查全局, 模式和游戏三层叠加后的每种时间组合
+    pub fn validate(&self) -> Result<()> {
+        self.timing.validate()?;
+
+        if !self.scene.hold.is_finite() || self.scene.hold < 0.0 {
+            return Err(Error::InvalidConfig(
+                "scene: hold must be a non-negative number".into(),
+            ));
+        }
+
+        if self.window_mode.fps_cap == 0 {
+            return Err(Error::InvalidConfig(
+                "window_mode: fps_cap must be positive".into(),
+            ));
+        }
+
+        for mode in [
+            &self.powersave,
+            &self.balance,
+            &self.performance,
+            &self.fast,
+        ] {
+            if mode
+                .thermal_min_ceiling
+                .is_some_and(|c| !(0.0..=1.0).contains(&c))
+            {
+                return Err(Error::InvalidConfig(
+                    "thermal_min_ceiling must be between 0.0 and 1.0".into(),
+                ));
+            }
+
+            let timing = mode.timing.apply(self.timing);
+            timing.validate()?;
+
+            for pkg in self.game_list.keys() {
+                if let Some(game) = self.game_timing(pkg)? {
+                    game.apply(timing).validate()?;
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    pub fn game_timing(&self, pkg: &str) -> Result<Option<TimingOverride>> {
+        match self.game_list.get(pkg).and_then(|v| v.get("timing")) {
+            Some(Value::Table(t)) => Ok(Some(t.clone().try_into()?)),
+            Some(_) => Err(Error::InvalidConfig(format!(
+                "timing of {pkg} must be a table"
+            ))),
+            None => Ok(None),
+        }
+    }
+}
+
 #[derive(Debug, Deserialize, Clone, Copy)]
 pub struct Config {
     pub keep_std: bool,
+    #[serde(default)]
+    pub reassert_freq: bool,
+    #[serde(default)]
+    pub aggregation: Aggregation,
+}
+
+#[derive(Debug, Deserialize, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum Aggregation {
+    #[default]
+    Primary,
+    Worst,
+    Weighted,
+}
+
+#[derive(Debug, Deserialize, Clone, Default)]
+#[serde(default)]
+pub struct ThermalConfig {
+    pub sensors: Vec<String>,
+    pub skin_sensors: Vec<String>,
+}
+
+#[derive(Debug, Deserialize, Clone, Copy)]
+#[serde(default)]
+pub struct SceneConfig {
+    pub loading_usage: f64,
+    pub idle_usage: f64,
+    pub idle_deviation: f64,
+    pub hold: f64,
+}
+
+impl Default for SceneConfig {
+    fn default() -> Self {
+        Self {
+            loading_usage: 0.6,
+            idle_usage: 0.25,
+            idle_deviation: 0.1,
+            hold: 2.0,
+        }
+    }
+}
+
+// 游戏不是全屏时的处理
+#[derive(Debug, Deserialize, Clone, Copy)]
+#[serde(default)]
+pub struct WindowModeConfig {
+    pub split: WindowAction,
+    pub freeform: WindowAction,
+    pub pip: WindowAction,
+    pub fps_cap: u32,
+}
+
+impl Default for WindowModeConfig {
+    fn default() -> Self {
+        Self {
+            split: WindowAction::Cap,
+            freeform: WindowAction::Cap,
+            pip: WindowAction::Suspend,
+            fps_cap: 60,
+        }
+    }
+}
+
+#[derive(Debug, Deserialize, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum WindowAction {
+    #[default]
+    Keep,
+    Cap,
+    Suspend,
+}
+
+#[derive(Debug, Deserialize, Clone)]
+#[serde(default)]
+pub struct AutoModeConfig {
+    pub enable: bool,
+    pub manual_priority: u64,
+    pub rules: Vec<AutoModeRule>,
+}
+
+impl Default for AutoModeConfig {
+    fn default() -> Self {
+        Self {
+            enable: false,
+            manual_priority: 600,
+            rules: Vec::new(),
+        }
+    }
+}
+
+#[derive(Debug, Deserialize, Clone, Copy)]
+pub struct AutoModeRule {
+    pub mode: Mode,
+    pub charging: Option<bool>,
+    pub battery_saver: Option<bool>,
+    pub capacity_below: Option<u32>,
+    pub capacity_above: Option<u32>,
+    pub temp_above: Option<f64>,
 }
 
 #[derive(Debug, Deserialize, Clone, Copy)]
 pub struct ModeConfig {
     pub fas_boost: bool,
     pub use_performance_governor: bool,
+    #[serde(default)]
+    pub cpu_backend: CpuBackend,
+    pub thermal_threshold: Option<f64>,
+    pub thermal_limit: Option<f64>,
+    pub thermal_min_ceiling: Option<f64>,
+    pub skin_setpoint: Option<f64>,
+    #[serde(default)]
+    pub timing: TimingOverride,
     pub scale: f64,
     pub jank_scale: f64,
     pub big_jank_scale: f64,
+    pub p99_jank_scale: Option<f64>,
+    #[serde(default)]
+    pub perfdog_jank: bool,
+    #[serde(default)]
+    pub loading_action: SceneAction,
+    #[serde(default)]
+    pub idle_action: SceneAction,
+}
+
+#[derive(Debug, Deserialize, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum SceneAction {
+    #[default]
+    None,
+    Boost,
+    Limit,
+}
+
+#[derive(Debug, Deserialize, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum CpuBackend {
+    #[default]
+    Cpufreq,
+    Uclamp,
 }
@@ -20,10 +20,29 @@ use toml::{Table, Value};
 use super::Config;
 use crate::framework::error::{Error, Result};
 
+// 标准配置中不填的可选模式参数, 合并时保留用户设置
+const OPTIONAL_MODE_KEYS: [&str; 5] = [
+    "thermal_threshold",
+    "thermal_limit",
+    "thermal_min_ceiling",
+    "skin_setpoint",
+    "p99_jank_scale",
+];
+
 #[derive(Deserialize, Serialize)]
 struct ConfigData {
     pub config: Table,
     pub game_list: Table,
+    #[serde(default)]
+    pub thermal: Table,
+    #[serde(default)]
+    pub auto_mode: Table,
+    #[serde(default)]
+    pub timing: Table,
+    #[serde(default)]
+    pub scene: Table,
+    #[serde(default)]
+    pub window_mode: Table,
     pub powersave: Table,
     pub balance: Table,
     pub performance: Table,
@@ -47,6 +66,11 @@ impl Config {
             let new_conf = ConfigData {
                 config: std_conf.config,
                 game_list: local_conf.game_list,
+                thermal: std_conf.thermal,
+                auto_mode: std_conf.auto_mode,
+                timing: std_conf.timing,
+                scene: std_conf.scene,
+                window_mode: std_conf.window_mode,
                 powersave: std_conf.powersave,
                 balance: std_conf.balance,
                 performance: std_conf.performance,
@@ -56,14 +80,24 @@ impl Config {
         }
 
         let config = Self::table_merge(std_conf.config, local_conf.config);
-        let powersave = Self::table_merge(std_conf.powersave, local_conf.powersave);
-        let balance = Self::table_merge(std_conf.balance, local_conf.balance);
-        let performance = Self::table_merge(std_conf.performance, local_conf.performance);
-        let fast = Self::table_merge(std_conf.fast, local_conf.fast);
+        let thermal = Self::table_merge(std_conf.thermal, local_conf.thermal);
+        let auto_mode = Self::table_merge(std_conf.auto_mode, local_conf.auto_mode);
+        let timing = Self::table_merge(std_conf.timing, local_conf.timing);
+        let scene = Self::table_merge(std_conf.scene, local_conf.scene);
+        let window_mode = Self::table_merge(std_conf.window_mode, local_conf.window_mode);
+        let powersave = Self::mode_merge(std_conf.powersave, local_conf.powersave, &timing);
+        let balance = Self::mode_merge(std_conf.balance, local_conf.balance, &timing);
+        let performance = Self::mode_merge(std_conf.performance, local_conf.performance, &timing);
+        let fast = Self::mode_merge(std_conf.fast, local_conf.fast, &timing);
 
         let new_conf = ConfigData {
             config,
             game_list: local_conf.game_list,
+            thermal,
+            auto_mode,
+            timing,
+            scene,
+            window_mode,
             powersave,
             balance,
             performance,
@@ -73,9 +107,85 @@ impl Config {
         Ok(toml::to_string(&new_conf)?)
     }
 
+    // 模式下的timing覆盖按全局[timing]的键逐个合并
+    fn mode_merge(s: Table, l: Table, timing: &Table) -> Table {
+        let mut optional: Table = l
+            .iter()
+            .filter(|(k, _)| OPTIONAL_MODE_KEYS.contains(&k.as_str()))
+            .map(|(k, v)| (k.clone(), v.clone()))
+            .collect();
+
+        if let Some(Value::Table(local_timing)) = l.get("timing") {
+            let local_timing: Table = local_timing
+                .iter()
+                .filter(|(k, _)| timing.contains_key(k.as_str()))
+                .map(|(k, v)| (k.clone(), v.clone()))
+                .collect();
+
+            if !local_timing.is_empty() {
+                optional.insert("timing".into(), Value::Table(local_timing));
+            }
+        }
+
+        let mut merged = Self::table_merge(s, l);
+        merged.extend(optional);
+        merged
+    }
+
     fn table_merge(mut s: Table, l: Table) -> Table {
         let old: Table = l.into_iter().filter(|(k, _)| s.contains_key(k)).collect();
         s.extend(old);
         s
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{framework::node::Mode, misc::Fixture};
+
+    #[test]
+    fn keep_optional_mode_keys() {
+        let std_conf = std::fs::read_to_string("module/games.toml").unwrap();
+        let local_conf = std_conf
+            .replace("keep_std = true", "keep_std = false")
+            .replace(
+                "[balance]\n",
+                "[balance.timing]\nwarmup = 5.0\nunknown = 1\n\n[balance]\nthermal_threshold = 75.0\nthermal_limit = 90.0\nthermal_min_ceiling = 0.5\nunknown = 1\n",
+            );
+
+        let merged: ConfigData =
+            toml::from_str(&Config::merge(&local_conf, &std_conf).unwrap()).unwrap();
+
+        assert_eq!(
+            merged.balance.get("thermal_threshold"),
+            Some(&Value::Float(75.0))
+        );
+        assert_eq!(
+            merged.balance.get("thermal_limit"),
+            Some(&Value::Float(90.0))
+        );
+        assert_eq!(
+            merged.balance.get("thermal_min_ceiling"),
+            Some(&Value::Float(0.5))
+        );
+        assert_eq!(merged.balance.get("unknown"), None);
+
+        let timing = merged
+            .balance
+            .get("timing")
+            .and_then(Value::as_table)
+            .unwrap();
+        assert_eq!(timing.get("warmup"), Some(&Value::Float(5.0)));
+        assert_eq!(timing.get("unknown"), None);
+        assert_eq!(merged.powersave.get("timing"), None);
+
+        // 合并结果可以被正常读取
+        let fixture = Fixture::new("merge");
+        let path = fixture.write("games.toml", Config::merge(&local_conf, &std_conf).unwrap());
+        let config = Config::new(path, "module/games.toml".into()).unwrap();
+        let timing = config.timing_config(Mode::Balance, None);
+        assert!((timing.warmup - 5.0).abs() < f64::EPSILON);
+        assert_eq!(merged.powersave.get("thermal_threshold"), None);
+    }
+}
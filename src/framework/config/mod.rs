@@ -14,26 +14,34 @@
 mod data;
 mod merge;
 mod read;
+mod timing;
 
-use std::{fs, path::Path, sync::Arc, thread};
+use std::{fs, path::Path, str::FromStr, sync::Arc, thread};
 
 use log::{error, info};
 use parking_lot::RwLock;
 use toml::Value;
 
 use crate::framework::{error::Result, node::Mode};
-use data::{ConfigData, ModeConfig};
-use read::wait_and_read;
+use data::{AutoModeConfig, Config as GlobalConfig, ConfigData, ModeConfig, ThermalConfig};
+use read::{wait_and_read, Listeners};
+
+pub use data::{
+    Aggregation, AutoModeRule, CpuBackend, SceneAction, SceneConfig, WindowAction, WindowModeConfig,
+};
+pub use timing::TimingConfig;
 
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub enum TargetFps {
     Value(u32),
     Array(Vec<u32>),
+    Auto,
 }
 
 #[derive(Debug, Clone)]
 pub struct Config {
     toml: Arc<RwLock<ConfigData>>,
+    listeners: Listeners,
 }
 
 impl Config {
@@ -44,24 +52,33 @@ impl Config {
         let ori = fs::read_to_string(path)?;
 
         let toml: ConfigData = toml::from_str(&ori)?;
+        toml.validate()?;
         let toml = Arc::new(RwLock::new(toml));
+        let listeners = Listeners::default();
 
         {
             let path = path.to_owned();
             let std_path = std_path.to_owned();
             let toml = toml.clone();
+            let listeners = listeners.clone();
 
             thread::Builder::new()
                 .name("ConfigThread".into())
                 .spawn(move || {
-                    wait_and_read(&path, &std_path, &toml).unwrap_or_else(|e| error!("{e:#?}"));
+                    wait_and_read(&path, &std_path, &toml, &listeners)
+                        .unwrap_or_else(|e| error!("{e:#?}"));
                     panic!("An unrecoverable error occurred!");
                 })?;
         }
 
         info!("Config watcher started");
 
-        Ok(Self { toml })
+        Ok(Self { toml, listeners })
+    }
+
+    // 配置热重载后调用f
+    pub fn on_reload<F: Fn() + Send + 'static>(&self, f: F) {
+        self.listeners.push(f);
     }
 
     pub fn target_fps<S: AsRef<str>>(&self, pkg: S) -> Option<TargetFps> {
@@ -74,6 +91,18 @@ impl Config {
 
         drop(toml); // early-drop Rwlock
 
+        // 表形式: { target_fps = [30, 60], mode = "performance" }
+        let value = match value {
+            Value::Table(t) => {
+                let Some(value) = t.get("target_fps") else {
+                    error!("Find target game {pkg} in config, but missing target_fps");
+                    return None;
+                };
+                value.clone()
+            }
+            value => value,
+        };
+
         match value {
             Value::Array(arr) => {
                 let mut arr: Vec<_> = arr
@@ -87,7 +116,7 @@ impl Config {
             Value::Integer(i) => Some(TargetFps::Value(i as u32)),
             Value::String(s) => {
                 if s == "auto" {
-                    Some(TargetFps::Array(vec![30, 45, 60, 90, 120, 144]))
+                    Some(TargetFps::Auto)
                 } else {
                     error!("Find target game {pkg} in config, but meet illegal data type");
                     error!("Sugg: try \'{pkg} = \"auto\"\'");
@@ -102,6 +131,19 @@ impl Config {
         }
     }
 
+    pub fn game_mode<S: AsRef<str>>(&self, pkg: S) -> Option<Mode> {
+        let pkg = pkg.as_ref();
+        let pkg = pkg.split(':').next()?;
+
+        let toml = self.toml.read();
+        let mode = toml.game_list.get(pkg)?.get("mode")?.as_str()?.to_string();
+        drop(toml); // early-drop Rwlock
+
+        Mode::from_str(&mode)
+            .map_err(|_| error!("Find target game {pkg} in config, but meet illegal mode {mode}"))
+            .ok()
+    }
+
     #[must_use]
     pub fn mode_config(&self, m: Mode) -> ModeConfig {
         let toml = self.toml.read();
@@ -114,6 +156,52 @@ impl Config {
         }
     }
 
+    #[must_use]
+    pub fn global_config(&self) -> GlobalConfig {
+        self.toml.read().config
+    }
+
+    #[must_use]
+    pub fn thermal_config(&self) -> ThermalConfig {
+        self.toml.read().thermal.clone()
+    }
+
+    #[must_use]
+    pub fn auto_mode_config(&self) -> AutoModeConfig {
+        self.toml.read().auto_mode.clone()
+    }
+
+    // 全局 -> 模式 -> 游戏逐层覆盖
+    #[must_use]
+    pub fn timing_config(&self, m: Mode, pkg: Option<&str>) -> TimingConfig {
+        let toml = self.toml.read();
+
+        let timing = match m {
+            Mode::Powersave => toml.powersave.timing,
+            Mode::Balance => toml.balance.timing,
+            Mode::Performance => toml.performance.timing,
+            Mode::Fast => toml.fast.timing,
+        }
+        .apply(toml.timing);
+
+        let game = pkg
+            .and_then(|p| p.split(':').next())
+            .and_then(|p| toml.game_timing(p).ok().flatten());
+        drop(toml); // early-drop Rwlock
+
+        game.map_or(timing, |g| g.apply(timing))
+    }
+
+    #[must_use]
+    pub fn scene_config(&self) -> SceneConfig {
+        self.toml.read().scene
+    }
+
+    #[must_use]
+    pub fn window_mode_config(&self) -> WindowModeConfig {
+        self.toml.read().window_mode
+    }
+
     #[must_use]
     pub fn config(&self) -> ConfigData {
         self.toml.read().clone()
@@ -11,19 +11,46 @@
 *  WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
 *  See the License for the specific language governing permissions and
 *  limitations under the License. */
-use std::{fs, path::Path, sync::Arc, thread, time::Duration};
+use std::{fmt, fs, path::Path, sync::Arc, thread, time::Duration};
 
 use inotify::{Inotify, WatchMask};
 use log::{debug, error};
-use parking_lot::RwLock;
+use parking_lot::{Mutex, RwLock};
 
 use super::ConfigData;
 use crate::framework::error::Result;
 
+type Listener = Box<dyn Fn() + Send>;
+
+// 配置重新加载后的回调
+#[derive(Clone, Default)]
+pub(super) struct Listeners(Arc<Mutex<Vec<Listener>>>);
+
+impl fmt::Debug for Listeners {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("Listeners")
+            .field(&self.0.lock().len())
+            .finish()
+    }
+}
+
+impl Listeners {
+    pub fn push<F: Fn() + Send + 'static>(&self, f: F) {
+        self.0.lock().push(Box::new(f));
+    }
+
+    fn notify(&self) {
+        for f in self.0.lock().iter() {
+            f();
+        }
+    }
+}
+
 pub(super) fn wait_and_read(
     path: &Path,
     std_path: &Path,
     toml: &Arc<RwLock<ConfigData>>,
+    listeners: &Listeners,
 ) -> Result<()> {
     let mut retry_count = 0;
 
@@ -33,9 +60,17 @@ pub(super) fn wait_and_read(
     loop {
         if retry_count > 10 {
             error!("Too many read user config retries");
-            error!("Use std profile instead until we could read and parse user config");
 
-            *toml.write() = std_config.clone();
+            match std_config.validate() {
+                Ok(()) => {
+                    error!("Use std profile instead until we could read and parse user config");
+
+                    *toml.write() = std_config.clone();
+                    listeners.notify();
+                }
+                Err(e) => error!("Std profile is invalid too: {e}, keep using the previous config"),
+            }
+
             retry_count = 0;
 
             continue;
@@ -53,7 +88,13 @@ pub(super) fn wait_and_read(
                 continue;
             }
         };
-        *toml.write() = toml::from_str(&ori)?;
+        match parse(&ori) {
+            Ok(new_config) => {
+                *toml.write() = new_config;
+                listeners.notify();
+            }
+            Err(e) => error!("{e}, keep using the previous config"),
+        }
 
         // wait until file change
         let Ok(mut inotify) = Inotify::init() else {
@@ -69,3 +110,68 @@ pub(super) fn wait_and_read(
         }
     }
 }
+
+// 解析失败或者校验不通过的配置都不会被使用
+fn parse(s: &str) -> Result<ConfigData> {
+    let config: ConfigData = toml::from_str(s)?;
+    config.validate()?;
+
+    Ok(config)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::mpsc;
+
+    use super::*;
+    use crate::misc::Fixture;
+
+    const TIMEOUT: Duration = Duration::from_secs(5);
+
+    #[test]
+    fn parse_errors() {
+        let std_config = fs::read_to_string("module/games.toml").unwrap();
+
+        assert!(parse(&std_config).is_ok());
+        assert!(parse("[config\nkeep_std = true").is_err());
+        assert!(parse(&std_config.replace("timeout_frames = 10", "timeout_frames = 1")).is_err());
+    }
+
+    #[test]
+    fn keep_previous_config() {
+        let fixture = Fixture::new("config-read");
+        let std_config = fs::read_to_string("module/games.toml").unwrap();
+        let path = fixture.write("games.toml", &std_config);
+
+        let toml = Arc::new(RwLock::new(parse(&std_config).unwrap()));
+        let listeners = Listeners::default();
+        let (sx, rx) = mpsc::channel();
+        listeners.push(move || {
+            let _ = sx.send(());
+        });
+
+        {
+            let toml = toml.clone();
+            thread::spawn(move || {
+                wait_and_read(&path, Path::new("module/games.toml"), &toml, &listeners)
+            });
+        }
+        rx.recv_timeout(TIMEOUT).unwrap();
+
+        // 语法错误和校验失败都保留之前的配置, 读取线程继续运行
+        fixture.write("games.toml", "[config\nkeep_std = true");
+        fixture.write(
+            "games.toml",
+            std_config.replace("timeout_frames = 10", "timeout_frames = 1"),
+        );
+        assert!(rx.recv_timeout(Duration::from_millis(500)).is_err());
+        assert_eq!(toml.read().timing.timeout_frames, 10);
+
+        fixture.write(
+            "games.toml",
+            std_config.replace("timeout_frames = 10", "timeout_frames = 20"),
+        );
+        rx.recv_timeout(TIMEOUT).unwrap();
+        assert_eq!(toml.read().timing.timeout_frames, 20);
+    }
+}
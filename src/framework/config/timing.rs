@@ -0,0 +1,221 @@
+/* Copyright 2023 shadow3aaa@gitbub.com
+*
+*  Licensed under the Apache License, Version 2.0 (the "License");
+*  you may not use this file except in compliance with the License.
+*  You may obtain a copy of the License at
+*
+*      http://www.apache.org/licenses/LICENSE-2.0
+*
+*  Unless required by applicable law or agreed to in writing, software
+*  distributed under the License is distributed on an "AS IS" BASIS,
+*  WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+*  See the License for the specific language governing permissions and
+*  limitations under the License. */
+use std::time::Duration;
+
+use serde_derive::Deserialize;
+
+use crate::framework::error::{Error, Result};
+
+// 除warmup / timeout / target_dwell / pause_threshold / rewarm / buffer_gc / fresh(秒)外, 时间均以帧为单位, 随目标帧率缩放
+#[derive(Debug, Deserialize, Clone, Copy, PartialEq)]
+#[serde(default)]
+pub struct TimingConfig {
+    pub warmup: f64,
+    pub timeout: f64,
+    pub timeout_frames: u32,
+    pub recv_timeout_frames: u32,
+    pub control_interval: u32,
+    pub limit_delay: u32,
+    pub jank_limit_delay: u32,
+    pub big_jank_limit_delay: u32,
+    pub buffer_len: u32,
+    pub fps_margin: f64,
+    pub fps_tolerance: u32,
+    pub fps_hysteresis: f64,
+    pub target_dwell: f64,
+    pub pause_threshold: f64,
+    pub outlier_scale: f64,
+    pub rewarm: f64,
+    pub buffer_gc: f64,
+    pub fresh: f64,
+}
+
+impl Default for TimingConfig {
+    fn default() -> Self {
+        Self {
+            warmup: 10.0,
+            timeout: 5.0,
+            timeout_frames: 10,
+            recv_timeout_frames: 2,
+            control_interval: 1,
+            limit_delay: 1,
+            jank_limit_delay: 3,
+            big_jank_limit_delay: 5,
+            buffer_len: 3,
+            fps_margin: 3.0,
+            fps_tolerance: 10,
+            fps_hysteresis: 5.0,
+            target_dwell: 3.0,
+            pause_threshold: 0.5,
+            outlier_scale: 10.0,
+            rewarm: 1.0,
+            buffer_gc: 10.0,
+            fresh: 1.0,
+        }
+    }
+}
+
+impl TimingConfig {
+    pub fn warmup(&self) -> Duration {
+        Duration::from_secs_f64(self.warmup)
+    }
+
+    // 有目标帧率时按帧数计算, 否则按秒
+    pub fn timeout(&self, target_fps: Option<u32>) -> Duration {
+        target_fps.map_or_else(
+            || Duration::from_secs_f64(self.timeout),
+            |t| Duration::from_secs(1) * self.timeout_frames / t,
+        )
+    }
+
+    pub fn target_dwell(&self) -> Duration {
+        Duration::from_secs_f64(self.target_dwell)
+    }
+
+    pub fn rewarm(&self) -> Duration {
+        Duration::from_secs_f64(self.rewarm)
+    }
+
+    pub fn buffer_gc(&self) -> Duration {
+        Duration::from_secs_f64(self.buffer_gc)
+    }
+
+    pub fn fresh(&self) -> Duration {
+        Duration::from_secs_f64(self.fresh)
+    }
+
+    pub fn recv_timeout(&self, target_fps: Option<u32>) -> Duration {
+        target_fps.map_or(Duration::from_secs(1), |t| {
+            Duration::from_secs(1) * self.recv_timeout_frames / t
+        })
+    }
+
+    pub fn validate(&self) -> Result<()> {
+        let check = |ok: bool, msg: &str| {
+            if ok {
+                Ok(())
+            } else {
+                Err(Error::InvalidConfig(format!("timing: {msg}")))
+            }
+        };
+
+        check(
+            self.warmup.is_finite() && self.warmup >= 0.0,
+            "warmup must be a non-negative number",
+        )?;
+        check(
+            self.timeout.is_finite() && self.timeout > 0.0,
+            "timeout must be a positive number",
+        )?;
+        check(
+            self.recv_timeout_frames > 0,
+            "recv_timeout_frames must be positive",
+        )?;
+        check(
+            self.timeout_frames >= self.recv_timeout_frames,
+            "timeout_frames must not be less than recv_timeout_frames",
+        )?;
+        check(
+            self.control_interval > 0
+                && self.limit_delay > 0
+                && self.jank_limit_delay > 0
+                && self.big_jank_limit_delay > 0,
+            "control_interval and limit delays must be positive",
+        )?;
+        check(self.buffer_len > 0, "buffer_len must be positive")?;
+        check(
+            self.fps_margin.is_finite() && self.fps_margin >= 0.0,
+            "fps_margin must be a non-negative number",
+        )?;
+        check(
+            self.fps_hysteresis.is_finite() && self.fps_hysteresis >= 0.0,
+            "fps_hysteresis must be a non-negative number",
+        )?;
+        check(
+            self.target_dwell.is_finite() && self.target_dwell >= 0.0,
+            "target_dwell must be a non-negative number",
+        )?;
+        check(
+            self.pause_threshold.is_finite() && self.pause_threshold > 0.0,
+            "pause_threshold must be a positive number",
+        )?;
+        check(
+            self.outlier_scale.is_finite() && self.outlier_scale >= 1.0,
+            "outlier_scale must not be less than 1",
+        )?;
+        check(
+            self.rewarm.is_finite() && self.rewarm >= 0.0,
+            "rewarm must be a non-negative number",
+        )?;
+        check(
+            self.buffer_gc.is_finite() && self.buffer_gc > 0.0,
+            "buffer_gc must be a positive number",
+        )?;
+        check(
+            self.fresh.is_finite() && self.fresh > 0.0,
+            "fresh must be a positive number",
+        )
+    }
+}
+
+// 模式或游戏中的覆盖项, 未填写的沿用上一层
+#[derive(Debug, Deserialize, Clone, Copy, Default)]
+#[serde(default)]
+pub struct TimingOverride {
+    pub warmup: Option<f64>,
+    pub timeout: Option<f64>,
+    pub timeout_frames: Option<u32>,
+    pub recv_timeout_frames: Option<u32>,
+    pub control_interval: Option<u32>,
+    pub limit_delay: Option<u32>,
+    pub jank_limit_delay: Option<u32>,
+    pub big_jank_limit_delay: Option<u32>,
+    pub buffer_len: Option<u32>,
+    pub fps_margin: Option<f64>,
+    pub fps_tolerance: Option<u32>,
+    pub fps_hysteresis: Option<f64>,
+    pub target_dwell: Option<f64>,
+    pub pause_threshold: Option<f64>,
+    pub outlier_scale: Option<f64>,
+    pub rewarm: Option<f64>,
+    pub buffer_gc: Option<f64>,
+    pub fresh: Option<f64>,
+}
+
+impl TimingOverride {
+    pub fn apply(&self, base: TimingConfig) -> TimingConfig {
+        TimingConfig {
+            warmup: self.warmup.unwrap_or(base.warmup),
+            timeout: self.timeout.unwrap_or(base.timeout),
+            timeout_frames: self.timeout_frames.unwrap_or(base.timeout_frames),
+            recv_timeout_frames: self.recv_timeout_frames.unwrap_or(base.recv_timeout_frames),
+            control_interval: self.control_interval.unwrap_or(base.control_interval),
+            limit_delay: self.limit_delay.unwrap_or(base.limit_delay),
+            jank_limit_delay: self.jank_limit_delay.unwrap_or(base.jank_limit_delay),
+            big_jank_limit_delay: self
+                .big_jank_limit_delay
+                .unwrap_or(base.big_jank_limit_delay),
+            buffer_len: self.buffer_len.unwrap_or(base.buffer_len),
+            fps_margin: self.fps_margin.unwrap_or(base.fps_margin),
+            fps_tolerance: self.fps_tolerance.unwrap_or(base.fps_tolerance),
+            fps_hysteresis: self.fps_hysteresis.unwrap_or(base.fps_hysteresis),
+            target_dwell: self.target_dwell.unwrap_or(base.target_dwell),
+            pause_threshold: self.pause_threshold.unwrap_or(base.pause_threshold),
+            outlier_scale: self.outlier_scale.unwrap_or(base.outlier_scale),
+            rewarm: self.rewarm.unwrap_or(base.rewarm),
+            buffer_gc: self.buffer_gc.unwrap_or(base.buffer_gc),
+            fresh: self.fresh.unwrap_or(base.fresh),
+        }
+    }
+}
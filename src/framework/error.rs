@@ -35,6 +35,8 @@ pub enum Error {
         #[from]
         source: toml::de::Error,
     },
+    #[error("Invalid config: {0}")]
+    InvalidConfig(String),
     #[error("Missing {0} when building Scheduler")]
     SchedulerMissing(&'static str),
     #[error("Got an io error: {source:?}")]
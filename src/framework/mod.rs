@@ -30,4 +30,17 @@ pub trait PerformanceController: Send {
     fn release_max(&self, m: Mode, c: &Config) -> Result<()>;
     fn init_game(&self, m: Mode, c: &Config) -> Result<()>;
     fn init_default(&self, m: Mode, c: &Config) -> Result<()>;
+
+    // 控制器所管理资源的繁忙程度(0.0 ~ 1.0), 用于判断瓶颈, 未知时为None
+    fn usage(&self) -> Option<f64> {
+        None
+    }
+
+    // 温控给出的性能上限(0.0 ~ 1.0), 1.0为不限制
+    fn set_ceiling(&self, _ceiling: f64) {}
+
+    // 需要发布到状态节点的信息, (节点名, 内容)
+    fn status(&self) -> Vec<(String, String)> {
+        Vec::new()
+    }
 }
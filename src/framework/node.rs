@@ -14,17 +14,23 @@
 use std::{
     collections::HashMap,
     fs,
-    path::Path,
+    path::{Path, PathBuf},
     str::FromStr,
+    thread,
     time::{Duration, Instant},
 };
 
+use inotify::{EventMask, Inotify, WatchMask};
+use log::error;
+use serde_derive::Deserialize;
+
 use super::error::{Error, Result};
 
 const NODE_PATH: &str = "/dev/fas_rs";
 const REFRESH_TIME: Duration = Duration::from_secs(1);
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[derive(Debug, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
 pub enum Mode {
     Powersave,
     Balance,
@@ -59,6 +65,7 @@ impl ToString for Mode {
 }
 
 pub struct Node {
+    root: PathBuf,
     map: HashMap<String, (String, Instant)>,
     mode: Mode,
     mode_timer: Instant,
@@ -66,10 +73,16 @@ pub struct Node {
 
 impl Node {
     pub fn init() -> Result<Self> {
-        let _ = fs::remove_dir_all(NODE_PATH);
-        fs::create_dir(NODE_PATH)?;
+        Self::init_at(NODE_PATH)
+    }
+
+    pub fn init_at<P: AsRef<Path>>(root: P) -> Result<Self> {
+        let root = root.as_ref();
+        let _ = fs::remove_dir_all(root);
+        fs::create_dir(root)?;
 
         let mut result = Self {
+            root: root.to_path_buf(),
             map: HashMap::new(),
             mode: Mode::Balance,
             mode_timer: Instant::now(),
@@ -83,7 +96,7 @@ impl Node {
         let id = i.as_ref();
         let default = d.as_ref();
 
-        let path = Path::new(NODE_PATH).join(id);
+        let path = self.root.join(id);
         fs::write(path, default)?;
 
         self.map
@@ -93,21 +106,102 @@ impl Node {
         Ok(())
     }
 
+    pub fn update_node<S: AsRef<str>>(&mut self, i: S, v: S) -> Result<()> {
+        let id = i.as_ref();
+        let value = v.as_ref();
+
+        let path = self.root.join(id);
+        fs::write(path, value)?;
+
+        self.map
+            .insert(id.to_string(), (value.to_string(), Instant::now()));
+
+        Ok(())
+    }
+
     pub fn get_mode(&mut self) -> Result<Mode> {
         if self.mode_timer.elapsed() > REFRESH_TIME {
-            self.mode = Self::read_mode()?;
+            self.mode = self.read_mode()?;
             self.mode_timer = Instant::now();
         }
 
         Ok(self.mode)
     }
 
+    // 收到mode节点的写入通知后立即重新读取
+    pub fn refresh_mode(&mut self) -> Result<()> {
+        self.mode = self.read_mode()?;
+        self.mode_timer = Instant::now();
+
+        Ok(())
+    }
+
+    // mode节点被写入时调用f, 不需要轮询节点
+    pub fn watch_mode<F: Fn() + Send + 'static>(f: F) -> Result<()> {
+        Self::watch(Path::new(NODE_PATH).join("mode"), f)
+    }
+
+    // 只在第一次, 读取出错或者节点被删除(IGNORED)后重新添加监听
+    fn watch<F: Fn() + Send + 'static>(path: PathBuf, f: F) -> Result<()> {
+        let mut inotify = Inotify::init()?;
+
+        thread::Builder::new()
+            .name("ModeWatcher".into())
+            .spawn(move || {
+                let mut buffer = [0; 1024];
+                let mut watching = false;
+
+                loop {
+                    if !watching {
+                        if let Err(e) = inotify.watches().add(&path, WatchMask::CLOSE_WRITE) {
+                            error!("Failed to watch mode node: {e}");
+                            thread::sleep(Duration::from_secs(1));
+                            continue;
+                        }
+
+                        watching = true;
+                    }
+
+                    let mut written = false;
+                    match inotify.read_events_blocking(&mut buffer) {
+                        Ok(events) => {
+                            for event in events {
+                                written |= event.mask.contains(EventMask::CLOSE_WRITE);
+                                watching &= !event.mask.contains(EventMask::IGNORED);
+                            }
+                        }
+                        Err(e) => {
+                            error!("Failed to read mode node events: {e}");
+                            watching = false;
+                            thread::sleep(Duration::from_secs(1));
+                        }
+                    }
+
+                    if written {
+                        f();
+                    }
+                }
+            })?;
+
+        Ok(())
+    }
+
+    pub fn set_mode(&mut self, mode: Mode) -> Result<()> {
+        let path = self.root.join("mode");
+        fs::write(path, mode.to_string())?;
+
+        self.mode = mode;
+        self.mode_timer = Instant::now();
+
+        Ok(())
+    }
+
     pub fn get_node<S: AsRef<str>>(&mut self, i: S) -> Result<String> {
         let id = i.as_ref();
 
         if let Some((value, stamp)) = self.map.get_mut(id) {
             if stamp.elapsed() > REFRESH_TIME {
-                let path = Path::new(NODE_PATH).join(id);
+                let path = self.root.join(id);
                 *value = fs::read_to_string(path)?;
                 *stamp = Instant::now();
             }
@@ -118,8 +212,8 @@ impl Node {
         }
     }
 
-    fn read_mode() -> Result<Mode> {
-        let path = Path::new(NODE_PATH).join("mode");
+    fn read_mode(&self) -> Result<Mode> {
+        let path = self.root.join("mode");
 
         Mode::from_str(
             fs::read_to_string(path)
@@ -128,3 +222,38 @@ impl Node {
         )
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::sync::mpsc;
+
+    use super::*;
+    use crate::misc::Fixture;
+
+    const TIMEOUT: Duration = Duration::from_secs(5);
+
+    #[test]
+    fn watch_rewatches_after_ignored() {
+        let fixture = Fixture::new("node-watch");
+        let path = fixture.write("mode", "balance");
+        let (sx, rx) = mpsc::channel();
+
+        Node::watch(path.clone(), move || {
+            let _ = sx.send(());
+        })
+        .unwrap();
+        thread::sleep(Duration::from_millis(100));
+
+        fixture.write("mode", "fast");
+        rx.recv_timeout(TIMEOUT).unwrap();
+
+        // 节点被重新创建后监听仍然有效
+        fs::remove_file(&path).unwrap();
+        fixture.write("mode", "powersave");
+        thread::sleep(Duration::from_millis(1500));
+        while rx.try_recv().is_ok() {}
+
+        fixture.write("mode", "performance");
+        rx.recv_timeout(TIMEOUT).unwrap();
+    }
+}
@@ -0,0 +1,74 @@
+/* Copyright 2023 shadow3aaa@gitbub.com
+*
+*  Licensed under the Apache License, Version 2.0 (the "License");
+*  you may not use this file except in compliance with the License.
+*  You may obtain a copy of the License at
+*
+*      http://www.apache.org/licenses/LICENSE-2.0
+*
+*  Unless required by applicable law or agreed to in writing, software
+*  distributed under the License is distributed on an "AS IS" BASIS,
+*  WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+*  See the License for the specific language governing permissions and
+*  limitations under the License. */
+use std::{fs, path::Path, process::Command};
+
+use crate::framework::config::AutoModeRule;
+
+const BATTERY_PATH: &str = "/sys/class/power_supply/battery";
+
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Battery {
+    pub capacity: Option<u32>,
+    pub charging: bool,
+    pub temp: Option<f64>,
+    pub saver: bool,
+}
+
+impl Battery {
+    // 省电模式需要启动settings进程读取, 由调用者决定是否读取
+    pub fn read(saver: bool) -> Self {
+        let path = Path::new(BATTERY_PATH);
+
+        let capacity = Self::read_node(&path.join("capacity")).and_then(|c| c.parse().ok());
+        let charging =
+            Self::read_node(&path.join("status")).is_some_and(|s| s == "Charging" || s == "Full");
+        // 单位为0.1℃
+        let temp = Self::read_node(&path.join("temp"))
+            .and_then(|t| t.parse::<f64>().ok())
+            .map(|t| t / 10.0);
+
+        Self {
+            capacity,
+            charging,
+            temp,
+            saver,
+        }
+    }
+
+    pub fn matches(&self, rule: &AutoModeRule) -> bool {
+        rule.charging.is_none_or(|c| c == self.charging)
+            && rule.battery_saver.is_none_or(|s| s == self.saver)
+            && rule
+                .capacity_below
+                .is_none_or(|b| self.capacity.is_some_and(|c| c < b))
+            && rule
+                .capacity_above
+                .is_none_or(|a| self.capacity.is_some_and(|c| c > a))
+            && rule
+                .temp_above
+                .is_none_or(|a| self.temp.is_some_and(|t| t > a))
+    }
+
+    fn read_node(path: &Path) -> Option<String> {
+        fs::read_to_string(path).ok().map(|s| s.trim().to_string())
+    }
+
+    // 系统省电模式
+    pub fn read_saver() -> bool {
+        Command::new("settings")
+            .args(["get", "global", "low_power"])
+            .output()
+            .is_ok_and(|o| String::from_utf8_lossy(&o.stdout).trim() == "1")
+    }
+}
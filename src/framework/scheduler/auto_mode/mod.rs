@@ -0,0 +1,178 @@
+/* Copyright 2023 shadow3aaa@gitbub.com
+*
+*  Licensed under the Apache License, Version 2.0 (the "License");
+*  you may not use this file except in compliance with the License.
+*  You may obtain a copy of the License at
+*
+*      http://www.apache.org/licenses/LICENSE-2.0
+*
+*  Unless required by applicable law or agreed to in writing, software
+*  distributed under the License is distributed on an "AS IS" BASIS,
+*  WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+*  See the License for the specific language governing permissions and
+*  limitations under the License. */
+mod battery;
+
+use std::time::{Duration, Instant};
+
+use log::info;
+
+use crate::framework::{
+    config::{AutoModeRule, Config},
+    error::Result,
+    node::{Mode, Node},
+};
+use battery::Battery;
+
+const REFRESH_TIME: Duration = Duration::from_secs(5);
+const SAVER_REFRESH_TIME: Duration = Duration::from_mins(1);
+
+// 根据电池状态自动切换模式, 手动写入mode节点在一段时间内优先
+pub struct AutoMode {
+    manual: Mode,
+    auto: Option<Mode>,
+    manual_since: Option<Instant>,
+    timer: Instant,
+    saver: bool,
+    saver_timer: Option<Instant>,
+}
+
+impl AutoMode {
+    pub fn new() -> Self {
+        Self {
+            manual: Mode::Balance,
+            auto: None,
+            manual_since: None,
+            timer: Instant::now(),
+            saver: false,
+            saver_timer: None,
+        }
+    }
+
+    pub fn update(&mut self, config: &Config, node: &mut Node) -> Result<Mode> {
+        let node_mode = node.get_mode()?;
+
+        // 节点内容与fas-rs写入的不同, 说明是手动切换
+        if node_mode != self.expected() {
+            info!("Mode manually switched to {}", node_mode.to_string());
+
+            self.manual = node_mode;
+            self.auto = None;
+            self.manual_since = Some(Instant::now());
+        }
+
+        if self.timer.elapsed() < REFRESH_TIME {
+            return Ok(self.expected());
+        }
+        self.timer = Instant::now();
+
+        let auto_config = config.auto_mode_config();
+        let target = if !auto_config.enable
+            || self
+                .manual_since
+                .is_some_and(|t| t.elapsed() < Duration::from_secs(auto_config.manual_priority))
+        {
+            self.manual
+        } else {
+            let battery = Battery::read(self.saver(&auto_config.rules));
+
+            auto_config
+                .rules
+                .iter()
+                .find(|r| battery.matches(r))
+                .map_or(self.manual, |r| r.mode)
+        };
+
+        if target != self.expected() {
+            info!("Auto mode switched to {}", target.to_string());
+
+            node.set_mode(target)?;
+            self.auto = (target != self.manual).then_some(target);
+        }
+
+        Ok(target)
+    }
+
+    // 空闲时的最长等待时间, 未启用自动模式时只由事件唤醒
+    pub fn idle_timeout(config: &Config) -> Option<Duration> {
+        config.auto_mode_config().enable.then_some(REFRESH_TIME)
+    }
+
+    // 只有规则用到battery_saver时才读取, 并且读取间隔比电池状态长
+    fn saver(&mut self, rules: &[AutoModeRule]) -> bool {
+        if !rules.iter().any(|r| r.battery_saver.is_some()) {
+            self.saver_timer = None;
+            return false;
+        }
+
+        if self
+            .saver_timer
+            .is_none_or(|t| t.elapsed() >= SAVER_REFRESH_TIME)
+        {
+            self.saver = Battery::read_saver();
+            self.saver_timer = Some(Instant::now());
+        }
+
+        self.saver
+    }
+
+    fn expected(&self) -> Mode {
+        self.auto.unwrap_or(self.manual)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rules(toml: &str) -> Vec<AutoModeRule> {
+        #[derive(serde_derive::Deserialize)]
+        struct Rules {
+            rules: Vec<AutoModeRule>,
+        }
+
+        toml::from_str::<Rules>(toml).unwrap().rules
+    }
+
+    #[test]
+    fn saver_only_when_referenced() {
+        let mut auto = AutoMode::new();
+
+        let without = rules("[[rules]]\nmode = \"fast\"\ncharging = true\n");
+        assert!(!auto.saver(&without));
+        assert!(auto.saver_timer.is_none());
+
+        let with = rules("[[rules]]\nmode = \"powersave\"\nbattery_saver = true\n");
+        auto.saver(&with);
+        let read_at = auto.saver_timer.unwrap();
+
+        // 刷新间隔内不重复读取
+        auto.saver(&with);
+        assert_eq!(auto.saver_timer, Some(read_at));
+
+        auto.saver_timer = Instant::now().checked_sub(SAVER_REFRESH_TIME);
+        auto.saver(&with);
+        assert!(auto.saver_timer.unwrap() > read_at);
+    }
+
+    #[test]
+    fn battery_matches() {
+        let battery = Battery {
+            capacity: Some(15),
+            charging: false,
+            temp: Some(41.0),
+            saver: true,
+        };
+
+        let rules = rules(
+            "[[rules]]\nmode = \"fast\"\ncharging = true\n\n\
+             [[rules]]\nmode = \"powersave\"\nbattery_saver = true\ncapacity_below = 20\n\n\
+             [[rules]]\nmode = \"balance\"\ncapacity_above = 20\n\n\
+             [[rules]]\nmode = \"powersave\"\ntemp_above = 42.0\n",
+        );
+        let matched: Vec<_> = rules.iter().map(|r| battery.matches(r)).collect();
+
+        assert_eq!(matched, [false, true, false, false]);
+        assert!(!Battery::default().matches(&rules[1]));
+    }
+}
@@ -24,12 +24,18 @@ pub trait IRemoteService: binder::Interface + Send {
     fn sendData(
         &self,
         _arg_buffer: i64,
+        _arg_session: i64,
         _arg_Pkg: &str,
         _arg_pid: i32,
         _arg_FrameTimeNanos: i64,
         _arg_cpu: i32,
     ) -> binder::Result<bool>;
-    fn removeBuffer(&self, _arg_buffer: i64, _arg_pid: i32) -> binder::Result<()>;
+    fn removeBuffer(
+        &self,
+        _arg_buffer: i64,
+        _arg_session: i64,
+        _arg_pid: i32,
+    ) -> binder::Result<()>;
     fn getDefaultImpl() -> IRemoteServiceDefaultRef
     where
         Self: Sized,
@@ -53,6 +59,7 @@ pub trait IRemoteServiceAsync<P>: binder::Interface + Send {
     fn sendData<'a>(
         &'a self,
         _arg_buffer: i64,
+        _arg_session: i64,
         _arg_Pkg: &'a str,
         _arg_pid: i32,
         _arg_FrameTimeNanos: i64,
@@ -61,6 +68,7 @@ pub trait IRemoteServiceAsync<P>: binder::Interface + Send {
     fn removeBuffer(
         &self,
         _arg_buffer: i64,
+        _arg_session: i64,
         _arg_pid: i32,
     ) -> binder::BoxFuture<'_, binder::Result<()>>;
 }
@@ -75,12 +83,18 @@ pub trait IRemoteServiceAsyncServer: binder::Interface + Send {
     async fn sendData(
         &self,
         _arg_buffer: i64,
+        _arg_session: i64,
         _arg_Pkg: &str,
         _arg_pid: i32,
         _arg_FrameTimeNanos: i64,
         _arg_cpu: i32,
     ) -> binder::Result<bool>;
-    async fn removeBuffer(&self, _arg_buffer: i64, _arg_pid: i32) -> binder::Result<()>;
+    async fn removeBuffer(
+        &self,
+        _arg_buffer: i64,
+        _arg_session: i64,
+        _arg_pid: i32,
+    ) -> binder::Result<()>;
 }
 impl BnRemoteService {
     /// Create a new async binder service.
@@ -121,6 +135,7 @@ impl BnRemoteService {
             fn sendData(
                 &self,
                 _arg_buffer: i64,
+                _arg_session: i64,
                 _arg_Pkg: &str,
                 _arg_pid: i32,
                 _arg_FrameTimeNanos: i64,
@@ -128,15 +143,23 @@ impl BnRemoteService {
             ) -> binder::Result<bool> {
                 self._rt.block_on(self._inner.sendData(
                     _arg_buffer,
+                    _arg_session,
                     _arg_Pkg,
                     _arg_pid,
                     _arg_FrameTimeNanos,
                     _arg_cpu,
                 ))
             }
-            fn removeBuffer(&self, _arg_buffer: i64, _arg_pid: i32) -> binder::Result<()> {
-                self._rt
-                    .block_on(self._inner.removeBuffer(_arg_buffer, _arg_pid))
+            fn removeBuffer(
+                &self,
+                _arg_buffer: i64,
+                _arg_session: i64,
+                _arg_pid: i32,
+            ) -> binder::Result<()> {
+                self._rt.block_on(
+                    self._inner
+                        .removeBuffer(_arg_buffer, _arg_session, _arg_pid),
+                )
             }
         }
         let wrapped = Wrapper {
@@ -150,6 +173,7 @@ pub trait IRemoteServiceDefault: Send + Sync {
     fn sendData(
         &self,
         _arg_buffer: i64,
+        _arg_session: i64,
         _arg_Pkg: &str,
         _arg_pid: i32,
         _arg_FrameTimeNanos: i64,
@@ -157,7 +181,12 @@ pub trait IRemoteServiceDefault: Send + Sync {
     ) -> binder::Result<bool> {
         Err(binder::StatusCode::UNKNOWN_TRANSACTION.into())
     }
-    fn removeBuffer(&self, _arg_buffer: i64, _arg_pid: i32) -> binder::Result<()> {
+    fn removeBuffer(
+        &self,
+        _arg_buffer: i64,
+        _arg_session: i64,
+        _arg_pid: i32,
+    ) -> binder::Result<()> {
         Err(binder::StatusCode::UNKNOWN_TRANSACTION.into())
     }
 }
@@ -177,6 +206,7 @@ impl BpRemoteService {
     fn build_parcel_sendData(
         &self,
         _arg_buffer: i64,
+        _arg_session: i64,
         _arg_Pkg: &str,
         _arg_pid: i32,
         _arg_FrameTimeNanos: i64,
@@ -184,6 +214,7 @@ impl BpRemoteService {
     ) -> binder::Result<binder::binder_impl::Parcel> {
         let mut aidl_data = self.binder.prepare_transact()?;
         aidl_data.write(&_arg_buffer)?;
+        aidl_data.write(&_arg_session)?;
         aidl_data.write(_arg_Pkg)?;
         aidl_data.write(&_arg_pid)?;
         aidl_data.write(&_arg_FrameTimeNanos)?;
@@ -193,6 +224,7 @@ impl BpRemoteService {
     fn read_response_sendData(
         &self,
         _arg_buffer: i64,
+        _arg_session: i64,
         _arg_Pkg: &str,
         _arg_pid: i32,
         _arg_FrameTimeNanos: i64,
@@ -203,6 +235,7 @@ impl BpRemoteService {
             if let Some(_aidl_default_impl) = <Self as IRemoteService>::getDefaultImpl() {
                 return _aidl_default_impl.sendData(
                     _arg_buffer,
+                    _arg_session,
                     _arg_Pkg,
                     _arg_pid,
                     _arg_FrameTimeNanos,
@@ -221,22 +254,25 @@ impl BpRemoteService {
     fn build_parcel_removeBuffer(
         &self,
         _arg_buffer: i64,
+        _arg_session: i64,
         _arg_pid: i32,
     ) -> binder::Result<binder::binder_impl::Parcel> {
         let mut aidl_data = self.binder.prepare_transact()?;
         aidl_data.write(&_arg_buffer)?;
+        aidl_data.write(&_arg_session)?;
         aidl_data.write(&_arg_pid)?;
         Ok(aidl_data)
     }
     fn read_response_removeBuffer(
         &self,
         _arg_buffer: i64,
+        _arg_session: i64,
         _arg_pid: i32,
         _aidl_reply: std::result::Result<binder::binder_impl::Parcel, binder::StatusCode>,
     ) -> binder::Result<()> {
         if matches!(_aidl_reply, Err(binder::StatusCode::UNKNOWN_TRANSACTION)) {
             if let Some(_aidl_default_impl) = <Self as IRemoteService>::getDefaultImpl() {
-                return _aidl_default_impl.removeBuffer(_arg_buffer, _arg_pid);
+                return _aidl_default_impl.removeBuffer(_arg_buffer, _arg_session, _arg_pid);
             }
         }
         let _aidl_reply = _aidl_reply?;
@@ -251,6 +287,7 @@ impl IRemoteService for BpRemoteService {
     fn sendData(
         &self,
         _arg_buffer: i64,
+        _arg_session: i64,
         _arg_Pkg: &str,
         _arg_pid: i32,
         _arg_FrameTimeNanos: i64,
@@ -258,6 +295,7 @@ impl IRemoteService for BpRemoteService {
     ) -> binder::Result<bool> {
         let _aidl_data = self.build_parcel_sendData(
             _arg_buffer,
+            _arg_session,
             _arg_Pkg,
             _arg_pid,
             _arg_FrameTimeNanos,
@@ -270,6 +308,7 @@ impl IRemoteService for BpRemoteService {
         );
         self.read_response_sendData(
             _arg_buffer,
+            _arg_session,
             _arg_Pkg,
             _arg_pid,
             _arg_FrameTimeNanos,
@@ -277,20 +316,26 @@ impl IRemoteService for BpRemoteService {
             _aidl_reply,
         )
     }
-    fn removeBuffer(&self, _arg_buffer: i64, _arg_pid: i32) -> binder::Result<()> {
-        let _aidl_data = self.build_parcel_removeBuffer(_arg_buffer, _arg_pid)?;
+    fn removeBuffer(
+        &self,
+        _arg_buffer: i64,
+        _arg_session: i64,
+        _arg_pid: i32,
+    ) -> binder::Result<()> {
+        let _aidl_data = self.build_parcel_removeBuffer(_arg_buffer, _arg_session, _arg_pid)?;
         let _aidl_reply = self.binder.submit_transact(
             transactions::removeBuffer,
             _aidl_data,
             binder::binder_impl::FLAG_PRIVATE_LOCAL,
         );
-        self.read_response_removeBuffer(_arg_buffer, _arg_pid, _aidl_reply)
+        self.read_response_removeBuffer(_arg_buffer, _arg_session, _arg_pid, _aidl_reply)
     }
 }
 impl<P: binder::BinderAsyncPool> IRemoteServiceAsync<P> for BpRemoteService {
     fn sendData<'a>(
         &'a self,
         _arg_buffer: i64,
+        _arg_session: i64,
         _arg_Pkg: &'a str,
         _arg_pid: i32,
         _arg_FrameTimeNanos: i64,
@@ -298,6 +343,7 @@ impl<P: binder::BinderAsyncPool> IRemoteServiceAsync<P> for BpRemoteService {
     ) -> binder::BoxFuture<'a, binder::Result<bool>> {
         let _aidl_data = match self.build_parcel_sendData(
             _arg_buffer,
+            _arg_session,
             _arg_Pkg,
             _arg_pid,
             _arg_FrameTimeNanos,
@@ -318,6 +364,7 @@ impl<P: binder::BinderAsyncPool> IRemoteServiceAsync<P> for BpRemoteService {
             move |_aidl_reply| async move {
                 self.read_response_sendData(
                     _arg_buffer,
+                    _arg_session,
                     _arg_Pkg,
                     _arg_pid,
                     _arg_FrameTimeNanos,
@@ -330,9 +377,10 @@ impl<P: binder::BinderAsyncPool> IRemoteServiceAsync<P> for BpRemoteService {
     fn removeBuffer(
         &self,
         _arg_buffer: i64,
+        _arg_session: i64,
         _arg_pid: i32,
     ) -> binder::BoxFuture<'_, binder::Result<()>> {
-        let _aidl_data = match self.build_parcel_removeBuffer(_arg_buffer, _arg_pid) {
+        let _aidl_data = match self.build_parcel_removeBuffer(_arg_buffer, _arg_session, _arg_pid) {
             Ok(_aidl_data) => _aidl_data,
             Err(err) => return Box::pin(std::future::ready(Err(err))),
         };
@@ -346,7 +394,7 @@ impl<P: binder::BinderAsyncPool> IRemoteServiceAsync<P> for BpRemoteService {
                 )
             },
             move |_aidl_reply| async move {
-                self.read_response_removeBuffer(_arg_buffer, _arg_pid, _aidl_reply)
+                self.read_response_removeBuffer(_arg_buffer, _arg_session, _arg_pid, _aidl_reply)
             },
         )
     }
@@ -355,6 +403,7 @@ impl IRemoteService for binder::binder_impl::Binder<BnRemoteService> {
     fn sendData(
         &self,
         _arg_buffer: i64,
+        _arg_session: i64,
         _arg_Pkg: &str,
         _arg_pid: i32,
         _arg_FrameTimeNanos: i64,
@@ -362,14 +411,20 @@ impl IRemoteService for binder::binder_impl::Binder<BnRemoteService> {
     ) -> binder::Result<bool> {
         self.0.sendData(
             _arg_buffer,
+            _arg_session,
             _arg_Pkg,
             _arg_pid,
             _arg_FrameTimeNanos,
             _arg_cpu,
         )
     }
-    fn removeBuffer(&self, _arg_buffer: i64, _arg_pid: i32) -> binder::Result<()> {
-        self.0.removeBuffer(_arg_buffer, _arg_pid)
+    fn removeBuffer(
+        &self,
+        _arg_buffer: i64,
+        _arg_session: i64,
+        _arg_pid: i32,
+    ) -> binder::Result<()> {
+        self.0.removeBuffer(_arg_buffer, _arg_session, _arg_pid)
     }
 }
 fn on_transact(
@@ -381,12 +436,14 @@ fn on_transact(
     match _aidl_code {
         transactions::sendData => {
             let _arg_buffer: i64 = _aidl_data.read()?;
+            let _arg_session: i64 = _aidl_data.read()?;
             let _arg_Pkg: String = _aidl_data.read()?;
             let _arg_pid: i32 = _aidl_data.read()?;
             let _arg_FrameTimeNanos: i64 = _aidl_data.read()?;
             let _arg_cpu: i32 = _aidl_data.read()?;
             let _aidl_return = _aidl_service.sendData(
                 _arg_buffer,
+                _arg_session,
                 &_arg_Pkg,
                 _arg_pid,
                 _arg_FrameTimeNanos,
@@ -403,8 +460,9 @@ fn on_transact(
         }
         transactions::removeBuffer => {
             let _arg_buffer: i64 = _aidl_data.read()?;
+            let _arg_session: i64 = _aidl_data.read()?;
             let _arg_pid: i32 = _aidl_data.read()?;
-            let _aidl_return = _aidl_service.removeBuffer(_arg_buffer, _arg_pid);
+            let _aidl_return = _aidl_service.removeBuffer(_arg_buffer, _arg_session, _arg_pid);
             match &_aidl_return {
                 Ok(_aidl_return) => {
                     _aidl_reply.write(&binder::Status::from(binder::StatusCode::OK))?;
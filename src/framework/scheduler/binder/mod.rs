@@ -14,17 +14,13 @@
 #![allow(non_snake_case)]
 mod IRemoteService;
 
-use std::{
-    sync::mpsc::{self, Receiver, Sender},
-    thread,
-    time::Duration,
-};
+use std::{sync::mpsc::Sender, thread, time::Duration};
 
 use binder::{BinderFeatures, Interface};
 use log::{error, info};
 use parking_lot::Mutex;
 
-use super::{BinderMessage, FasData};
+use super::{BinderMessage, FasData, Producer};
 use crate::framework::{
     config::Config,
     error::{Error, Result},
@@ -42,6 +38,7 @@ impl IRemoteService::IRemoteService for FasServer {
     fn sendData(
         &self,
         buffer: i64,
+        session: i64,
         pkg: &str,
         pid: i32,
         frametime_ns: i64,
@@ -54,9 +51,8 @@ impl IRemoteService::IRemoteService for FasServer {
         let frametime = Duration::from_nanos(frametime_ns as u64);
 
         let data = FasData {
-            buffer,
+            producer: Producer::new(buffer, session, pid),
             target_fps,
-            pid,
             pkg: pkg.to_string(),
             frametime,
             cpu,
@@ -69,12 +65,9 @@ impl IRemoteService::IRemoteService for FasServer {
         Ok(true)
     }
 
-    fn removeBuffer(&self, buffer: i64, pid: i32) -> binder::Result<()> {
-        if let Err(e) = self
-            .sx
-            .lock()
-            .send(BinderMessage::RemoveBuffer((buffer, pid)))
-        {
+    fn removeBuffer(&self, buffer: i64, session: i64, pid: i32) -> binder::Result<()> {
+        let producer = Producer::new(buffer, session, pid);
+        if let Err(e) = self.sx.lock().send(BinderMessage::RemoveBuffer(producer)) {
             error!("{e:?}");
         }
 
@@ -83,14 +76,12 @@ impl IRemoteService::IRemoteService for FasServer {
 }
 
 impl FasServer {
-    pub fn run_server(config: Config) -> Result<Receiver<BinderMessage>> {
-        let (sx, rx) = mpsc::channel();
-
+    pub fn run_server(config: Config, sx: Sender<BinderMessage>) -> Result<()> {
         thread::Builder::new()
             .name("BinderServer".into())
             .spawn(|| Self::run(sx, config))?;
 
-        Ok(rx)
+        Ok(())
     }
 
     fn run(sx: Sender<BinderMessage>, config: Config) -> Result<()> {
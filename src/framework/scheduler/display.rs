@@ -0,0 +1,173 @@
+/* Copyright 2023 shadow3aaa@gitbub.com
+*
+*  Licensed under the Apache License, Version 2.0 (the "License");
+*  you may not use this file except in compliance with the License.
+*  You may obtain a copy of the License at
+*
+*      http://www.apache.org/licenses/LICENSE-2.0
+*
+*  Unless required by applicable law or agreed to in writing, software
+*  distributed under the License is distributed on an "AS IS" BASIS,
+*  WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+*  See the License for the specific language governing permissions and
+*  limitations under the License. */
+use std::{
+    process::Command,
+    time::{Duration, Instant},
+};
+
+const REFRESH_TIME: Duration = Duration::from_secs(5);
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct DisplayInfo {
+    pub refresh_rate: Option<u32>,
+    pub modes: Vec<u32>,
+}
+
+pub struct DisplayWatcher {
+    info: DisplayInfo,
+    last_refresh: Instant,
+}
+
+impl DisplayWatcher {
+    pub fn new() -> Self {
+        Self {
+            info: Self::read().unwrap_or_default(),
+            last_refresh: Instant::now(),
+        }
+    }
+
+    pub const fn info(&self) -> &DisplayInfo {
+        &self.info
+    }
+
+    // 返回变化后的显示信息
+    pub fn update(&mut self) -> Option<&DisplayInfo> {
+        if self.last_refresh.elapsed() < REFRESH_TIME {
+            return None;
+        }
+        self.last_refresh = Instant::now();
+
+        let info = Self::read()?;
+        if info == self.info {
+            return None;
+        }
+
+        self.info = info;
+        Some(&self.info)
+    }
+
+    fn read() -> Option<DisplayInfo> {
+        let dump = Command::new("dumpsys").arg("display").output().ok()?;
+        let dump = String::from_utf8_lossy(&dump.stdout).into_owned();
+
+        Self::parse_display(&dump)
+    }
+
+    // DisplayDeviceInfo{..., modeId 2, renderFrameRate 120.0, ..., supportedModes [{id=1, ..., fps=60.0, ...}, {id=2, ..., fps=120.0, ...}], ...}
+    fn parse_display(dump: &str) -> Option<DisplayInfo> {
+        let line = dump
+            .lines()
+            .find(|l| l.contains("DisplayDeviceInfo") && l.contains("supportedModes"))?;
+
+        let supported: Vec<(u32, f64)> = line
+            .split("{id=")
+            .skip(1)
+            .filter_map(|mode| {
+                let id = mode.split(',').next()?.trim().parse().ok()?;
+                let fps = Self::parse_field(mode, "fps=")?;
+                Some((id, fps))
+            })
+            .collect();
+
+        let refresh_rate = Self::parse_field(line, "renderFrameRate ").or_else(|| {
+            let id = Self::parse_field(line, " modeId ")? as u32;
+            supported
+                .iter()
+                .find(|(i, _)| *i == id)
+                .map(|(_, fps)| *fps)
+        });
+
+        let mut modes: Vec<_> = supported
+            .iter()
+            .map(|(_, fps)| fps.round() as u32)
+            .collect();
+        modes.sort_unstable();
+        modes.dedup();
+
+        Some(DisplayInfo {
+            refresh_rate: refresh_rate.map(|r| r.round() as u32),
+            modes,
+        })
+    }
+
+    fn parse_field(s: &str, key: &str) -> Option<f64> {
+        let value = &s[s.find(key)? + key.len()..];
+        let end = value
+            .find(|c: char| !c.is_ascii_digit() && c != '.')
+            .unwrap_or(value.len());
+
+        value[..end].parse().ok()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Android 12 ~ 14
+    const RENDER_RATE: &str = r#"DISPLAY MANAGER (dumpsys display)
+  mOnlyCode=false
+Display Devices: size=1
+  DisplayDeviceInfo{"Built-in Screen": uniqueId="local:4630946545580055169", 1080 x 2400, modeId 2, renderFrameRate 120.0, defaultModeId 1, supportedModes [{id=1, width=1080, height=2400, fps=60.000004, alternativeRefreshRates=[120.00001]}, {id=2, width=1080, height=2400, fps=120.00001, alternativeRefreshRates=[60.000004]}], colorMode 0, supportedColorModes [0], hdrCapabilities HdrCapabilities{mSupportedHdrTypes=[], mMaxLuminance=500.0, mMaxAverageLuminance=500.0, mMinLuminance=0.0}, state ON, committedState ON, FLAG_DEFAULT_DISPLAY, FLAG_ROTATES_WITH_CONTENT, FLAG_SECURE, FLAG_SUPPORTS_PROTECTED_BUFFERS}
+    mAdapter=LocalDisplayAdapter
+"#;
+
+    // Android 10 / 11, 没有renderFrameRate, 通过modeId查找
+    const MODE_ID: &str = r#"Display Devices: size=2
+  DisplayDeviceInfo{"内置屏幕": uniqueId="local:0", 1080 x 2340, modeId 1, defaultModeId 1, supportedModes [{id=1, width=1080, height=2340, fps=90.0}, {id=2, width=1080, height=2340, fps=60.0}], colorMode 0, supportedColorModes [0], HdrCapabilities android.view.Display$HdrCapabilities@1d6311, density 440, 403.411 x 403.041 dpi, appVsyncOff 1000000, presDeadline 11111111, touch INTERNAL, rotation 0, type BUILT_IN, address {port=0}, state ON, FLAG_DEFAULT_DISPLAY, FLAG_ROTATES_WITH_CONTENT, FLAG_SECURE, FLAG_SUPPORTS_PROTECTED_BUFFERS}
+  DisplayDeviceInfo{"Overlay #1": uniqueId="overlay:1", 1920 x 1080, modeId 3, defaultModeId 3, supportedModes [{id=3, width=1920, height=1080, fps=30.0}], colorMode 0, state ON}
+"#;
+
+    #[test]
+    fn render_frame_rate() {
+        assert_eq!(
+            DisplayWatcher::parse_display(RENDER_RATE),
+            Some(DisplayInfo {
+                refresh_rate: Some(120),
+                modes: vec![60, 120],
+            })
+        );
+    }
+
+    #[test]
+    fn mode_id() {
+        assert_eq!(
+            DisplayWatcher::parse_display(MODE_ID),
+            Some(DisplayInfo {
+                refresh_rate: Some(90),
+                modes: vec![60, 90],
+            })
+        );
+    }
+
+    #[test]
+    fn malformed() {
+        assert_eq!(DisplayWatcher::parse_display(""), None);
+        assert_eq!(
+            DisplayWatcher::parse_display("DisplayDeviceInfo{\"Built-in Screen\", modeId 1}"),
+            None
+        );
+
+        // 损坏的模式被跳过, modeId找不到时刷新率未知
+        assert_eq!(
+            DisplayWatcher::parse_display(
+                "DisplayDeviceInfo{modeId 5, supportedModes [{id=x, fps=60.0}, {id=2, fps=}, {id=3, fps=144.0}]}"
+            ),
+            Some(DisplayInfo {
+                refresh_rate: None,
+                modes: vec![144],
+            })
+        );
+    }
+}
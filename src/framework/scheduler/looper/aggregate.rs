@@ -0,0 +1,319 @@
+/* Copyright 2023 shadow3aaa@gitbub.com
+*
+*  Licensed under the Apache License, Version 2.0 (the "License");
+*  you may not use this file except in compliance with the License.
+*  You may obtain a copy of the License at
+*
+*      http://www.apache.org/licenses/LICENSE-2.0
+*
+*  Unless required by applicable law or agreed to in writing, software
+*  distributed under the License is distributed on an "AS IS" BASIS,
+*  WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+*  See the License for the specific language governing permissions and
+*  limitations under the License. */
+use super::{
+    policy::{JankEvent, NormalEvent},
+    Buffer, Looper, Producer,
+};
+use crate::framework::{config::Aggregation, PerformanceController};
+
+// 聚合多个buffer的事件时使用的数值
+pub trait EventValue: Ord + Copy {
+    fn value(self) -> f64;
+    fn from_value(value: f64) -> Self;
+}
+
+impl EventValue for NormalEvent {
+    fn value(self) -> f64 {
+        match self {
+            Self::Restrictable => -1.0,
+            Self::None => 0.0,
+            Self::Release => 1.0,
+        }
+    }
+
+    fn from_value(value: f64) -> Self {
+        if value >= 0.5 {
+            Self::Release
+        } else if value <= -0.5 {
+            Self::Restrictable
+        } else {
+            Self::None
+        }
+    }
+}
+
+impl EventValue for JankEvent {
+    fn value(self) -> f64 {
+        match self {
+            Self::None => 0.0,
+            Self::Jank => 1.0,
+            Self::BigJank => 2.0,
+        }
+    }
+
+    fn from_value(value: f64) -> Self {
+        if value >= 1.5 {
+            Self::BigJank
+        } else if value >= 0.5 {
+            Self::Jank
+        } else {
+            Self::None
+        }
+    }
+}
+
+impl Buffer {
+    // 主渲染surface的可能性: 帧数多, 帧率贴近目标且稳定
+    pub fn score(&self) -> f64 {
+        let Some(target_fps) = self.target_fps else {
+            return 0.0;
+        };
+
+        let cadence = (self.current_fps / f64::from(target_fps)).min(1.0);
+        let stability = 1.0 / (1.0 + self.deviation);
+
+        self.frametimes.len() as f64 * cadence * stability
+    }
+
+    fn fresh(&self) -> bool {
+        self.last_update.elapsed() < self.timing.fresh()
+    }
+}
+
+impl<P: PerformanceController> Looper<P> {
+    pub fn primary_buffer(&self) -> Option<Producer> {
+        self.buffers
+            .iter()
+            .filter(|(_, buffer)| buffer.fresh())
+            .max_by(|(_, a), (_, b)| a.score().total_cmp(&b.score()))
+            .map(|(producer, _)| *producer)
+    }
+
+    // 参与决策的buffer及其权重
+    pub fn selected_buffers(&self) -> Vec<(Producer, f64)> {
+        match self.config.global_config().aggregation {
+            Aggregation::Primary => self
+                .primary_buffer()
+                .map(|producer| (producer, 1.0))
+                .into_iter()
+                .collect(),
+            Aggregation::Worst | Aggregation::Weighted => self
+                .buffers
+                .iter()
+                .filter(|(_, buffer)| buffer.fresh())
+                .map(|(producer, buffer)| (*producer, buffer.score()))
+                .collect(),
+        }
+    }
+
+    // 控制节奏和超时使用的目标帧率
+    pub fn control_target_fps(&self) -> Option<u32> {
+        match self.config.global_config().aggregation {
+            Aggregation::Primary | Aggregation::Weighted => self
+                .primary_buffer()
+                .and_then(|producer| self.buffers.get(&producer)?.target_fps),
+            Aggregation::Worst => self
+                .buffers
+                .values()
+                .filter(|buffer| buffer.fresh())
+                .filter_map(|buffer| buffer.target_fps)
+                .max(),
+        }
+    }
+
+    pub fn aggregate<E: EventValue>(&self, events: &[(E, f64)]) -> Option<E> {
+        let worst = events.iter().map(|(event, _)| *event).max()?;
+
+        if self.config.global_config().aggregation != Aggregation::Weighted {
+            return Some(worst);
+        }
+
+        let total: f64 = events.iter().map(|(_, weight)| weight).sum();
+        if total <= 0.0 {
+            return Some(worst);
+        }
+
+        let value = events
+            .iter()
+            .map(|(event, weight)| event.value() * weight)
+            .sum::<f64>()
+            / total;
+
+        Some(E::from_value(value))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        fs,
+        time::{Duration, Instant},
+    };
+
+    use super::{
+        super::tests::{looper, Recorder},
+        *,
+    };
+    use crate::{
+        framework::config::{Config, TargetFps},
+        misc::Fixture,
+    };
+
+    const GAME: Producer = Producer::new(1, 1, 1000);
+    const OVERLAY: Producer = Producer::new(2, 2, 1000);
+
+    // frames帧frametime, 目标帧率60
+    fn buffer(frames: usize, frametime: Duration, deviation: f64) -> Buffer {
+        let mut buffer = Buffer::new(TargetFps::Array(vec![30, 60]));
+        for _ in 0..frames {
+            buffer.push_frametime(frametime);
+        }
+        buffer.target_fps = Some(60);
+        buffer.deviation = deviation;
+
+        buffer
+    }
+
+    // 3D主画面和低帧率的UI层
+    fn game_looper(fixture: &Fixture, aggregation: &str) -> Looper<Recorder> {
+        let mut looper = looper(fixture);
+
+        let games = fs::read_to_string("module/games.toml").unwrap();
+        let path = fixture.write(
+            "games.toml",
+            games.replace(
+                "aggregation = \"primary\"",
+                &format!("aggregation = \"{aggregation}\""),
+            ),
+        );
+        looper.config = Config::new(path, "module/games.toml".into()).unwrap();
+
+        looper
+            .buffers
+            .insert(GAME, buffer(120, Duration::from_micros(16_667), 0.1));
+        looper
+            .buffers
+            .insert(OVERLAY, buffer(10, Duration::from_millis(100), 0.5));
+
+        looper
+    }
+
+    fn expire(looper: &mut Looper<Recorder>, producer: Producer) {
+        let buffer = looper.buffers.get_mut(&producer).unwrap();
+        buffer.last_update = Instant::now().checked_sub(buffer.timing.fresh()).unwrap();
+    }
+
+    #[test]
+    fn score() {
+        let game = buffer(120, Duration::from_micros(16_667), 0.1);
+        assert!((game.score() - 120.0 / 1.1).abs() < 0.1);
+
+        // 帧率不到目标时按比例降低
+        let overlay = buffer(10, Duration::from_millis(100), 0.5);
+        assert!((overlay.score() - 10.0 * (10.0 / 60.0) / 1.5).abs() < 0.01);
+
+        let mut unmatched = buffer(120, Duration::from_micros(16_667), 0.1);
+        unmatched.target_fps = None;
+        assert!(unmatched.score().abs() < f64::EPSILON);
+    }
+
+    #[test]
+    fn primary() {
+        let fixture = Fixture::new("aggregate-primary");
+        let mut looper = game_looper(&fixture, "primary");
+
+        assert_eq!(looper.primary_buffer(), Some(GAME));
+        assert_eq!(looper.selected_buffers(), [(GAME, 1.0)]);
+        assert_eq!(looper.control_target_fps(), Some(60));
+        assert_eq!(
+            looper.aggregate(&[(NormalEvent::Restrictable, 1.0)]),
+            Some(NormalEvent::Restrictable)
+        );
+        assert_eq!(looper.aggregate::<JankEvent>(&[]), None);
+
+        // 超过fresh没有新帧的buffer不参与
+        expire(&mut looper, GAME);
+        assert_eq!(looper.primary_buffer(), Some(OVERLAY));
+        expire(&mut looper, OVERLAY);
+        assert_eq!(looper.primary_buffer(), None);
+        assert!(looper.selected_buffers().is_empty());
+        assert_eq!(looper.control_target_fps(), None);
+    }
+
+    #[test]
+    fn worst() {
+        let fixture = Fixture::new("aggregate-worst");
+        let mut looper = game_looper(&fixture, "worst");
+
+        let mut selected = looper.selected_buffers();
+        selected.sort_by_key(|(producer, _)| producer.buffer);
+        assert_eq!(selected.len(), 2);
+        assert_eq!(selected[0].0, GAME);
+        assert_eq!(selected[1].0, OVERLAY);
+
+        // UI层的卡顿也会触发提升
+        assert_eq!(
+            looper.aggregate(&[
+                (NormalEvent::Restrictable, selected[0].1),
+                (NormalEvent::Release, selected[1].1),
+            ]),
+            Some(NormalEvent::Release)
+        );
+        assert_eq!(
+            looper.aggregate(&[
+                (JankEvent::None, selected[0].1),
+                (JankEvent::BigJank, selected[1].1),
+            ]),
+            Some(JankEvent::BigJank)
+        );
+
+        // 取新鲜buffer中最高的目标帧率
+        looper.buffers.get_mut(&OVERLAY).unwrap().target_fps = Some(30);
+        assert_eq!(looper.control_target_fps(), Some(60));
+        expire(&mut looper, GAME);
+        assert_eq!(looper.control_target_fps(), Some(30));
+        assert_eq!(looper.selected_buffers().len(), 1);
+    }
+
+    #[test]
+    fn weighted() {
+        let fixture = Fixture::new("aggregate-weighted");
+        let looper = game_looper(&fixture, "weighted");
+
+        let weight = |producer| {
+            looper
+                .selected_buffers()
+                .into_iter()
+                .find(|(p, _)| *p == producer)
+                .unwrap()
+                .1
+        };
+        let (game, overlay) = (weight(GAME), weight(OVERLAY));
+        assert!(game > overlay * 10.0);
+
+        // 按评分加权, UI层的卡顿被主画面稀释
+        assert_eq!(
+            looper.aggregate(&[
+                (NormalEvent::Restrictable, game),
+                (NormalEvent::Release, overlay),
+            ]),
+            Some(NormalEvent::Restrictable)
+        );
+        assert_eq!(
+            looper.aggregate(&[(JankEvent::None, game), (JankEvent::BigJank, overlay)]),
+            Some(JankEvent::None)
+        );
+        assert_eq!(
+            looper.aggregate(&[(JankEvent::Jank, game), (JankEvent::BigJank, overlay)]),
+            Some(JankEvent::Jank)
+        );
+
+        // 权重都为0时退化为取最差
+        assert_eq!(
+            looper.aggregate(&[(JankEvent::None, 0.0), (JankEvent::BigJank, 0.0)]),
+            Some(JankEvent::BigJank)
+        );
+        assert_eq!(looper.control_target_fps(), Some(60));
+    }
+}
@@ -11,29 +11,42 @@
 *  WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
 *  See the License for the specific language governing permissions and
 *  limitations under the License. */
-use std::{
-    collections::VecDeque,
-    time::{Duration, Instant},
-};
+use std::time::{Duration, Instant};
 
 #[cfg(debug_assertions)]
 use log::debug;
 
-use crate::framework::config::TargetFps;
+use super::{
+    jank::JankClassifier, policy::JankEvent, rolling::RollingWindow, scene::SceneDetector,
+};
+use crate::framework::{
+    config::{TargetFps, TimingConfig},
+    scheduler::display::DisplayInfo,
+};
 
-const BUFFER_LEN_SECS: usize = 3;
+const AUTO_TARGET_FPS: [u32; 6] = [30, 45, 60, 90, 120, 144];
 
 #[derive(Debug)]
 pub struct Buffer {
     pub target_fps: Option<u32>,
     pub current_fps: f64,
     pub avg_time: Duration,
-    pub frametimes: VecDeque<Duration>,
+    pub frametimes: RollingWindow,
     pub frame_prepare: Duration,
     pub deviation: f64,
     pub last_update: Instant,
+    pub start_time: Option<u64>,
     pub acc_frame: f64,
     pub acc_timer: Instant,
+    pub fps_cap: Option<u32>,
+    pub window_cap: Option<u32>,
+    pub timing: TimingConfig,
+    pub last_jank: JankEvent,
+    classifier: JankClassifier,
+    rewarm_timer: Option<Instant>,
+    pub scene: SceneDetector,
+    pub display: DisplayInfo,
+    target_candidate: Option<(Option<u32>, Instant)>,
     target_fps_config: TargetFps,
     timer: Instant,
 }
@@ -44,31 +57,78 @@ impl Buffer {
             target_fps: None,
             current_fps: 0.0,
             avg_time: Duration::ZERO,
-            frametimes: VecDeque::new(),
+            frametimes: RollingWindow::new(),
             frame_prepare: Duration::ZERO,
             deviation: 0.0,
             last_update: Instant::now(),
+            start_time: None,
             acc_frame: 0.0,
             acc_timer: Instant::now(),
+            fps_cap: None,
+            window_cap: None,
+            timing: TimingConfig::default(),
+            last_jank: JankEvent::None,
+            classifier: JankClassifier::default(),
+            rewarm_timer: None,
+            scene: SceneDetector::new(),
+            display: DisplayInfo::default(),
+            target_candidate: None,
             timer: Instant::now(),
             target_fps_config: t,
         }
     }
 
-    pub fn push_frametime(&mut self, d: Duration) {
+    // 返回这一帧是否计入统计
+    pub fn push_frametime(&mut self, d: Duration) -> bool {
         self.last_update = Instant::now();
         self.frame_prepare = Duration::ZERO;
 
-        self.frametimes.push_front(d);
+        // 暂停, 加载或者切换应用产生的帧, 不计入统计, 短暂重新预热
+        if self.is_outlier(d) {
+            #[cfg(debug_assertions)]
+            debug!("Outlier frame: {d:?}, rewarm");
+
+            self.rewarm_timer = Some(Instant::now());
+            self.classifier = JankClassifier::default();
+            self.last_jank = JankEvent::None;
+            self.acc_frame = 0.0;
+            self.acc_timer = Instant::now();
+            return false;
+        }
+
+        self.last_jank = self.classifier.classify(d);
+
         self.frametimes
-            .truncate(self.target_fps.unwrap_or(60) as usize * BUFFER_LEN_SECS);
+            .push(d, Duration::from_secs(self.timing.buffer_len.into()));
         self.calculate_current_fps();
         self.calculate_deviation();
 
-        if self.timer.elapsed() >= Duration::from_secs(BUFFER_LEN_SECS as u64) {
+        if self.timer.elapsed() >= Duration::from_secs(self.timing.buffer_len.into()) {
             self.timer = Instant::now();
             self.calculate_target_fps();
         }
+
+        true
+    }
+
+    pub fn rewarming(&self) -> bool {
+        self.rewarm_timer
+            .is_some_and(|t| t.elapsed() < self.timing.rewarm())
+    }
+
+    // 正在经历的帧已经长到像是暂停
+    pub fn pausing(&self) -> bool {
+        self.frame_prepare.as_secs_f64() > self.timing.pause_threshold
+    }
+
+    fn is_outlier(&self, d: Duration) -> bool {
+        if d.as_secs_f64() <= self.timing.pause_threshold {
+            return false;
+        }
+
+        self.frametimes.is_empty()
+            || d > self.frametimes.sum().mul_f64(self.timing.outlier_scale)
+                / self.frametimes.len() as u32
     }
 
     pub fn frame_prepare(&mut self) {
@@ -78,11 +138,11 @@ impl Buffer {
     }
 
     fn calculate_current_fps(&mut self) {
-        let avg_time: Duration = self
-            .frametimes
-            .iter()
-            .sum::<Duration>()
-            .saturating_add(self.frame_prepare)
+        if self.frametimes.is_empty() {
+            return;
+        }
+
+        let avg_time: Duration = self.frametimes.sum().saturating_add(self.frame_prepare)
             / self.frametimes.len().try_into().unwrap();
         #[cfg(debug_assertions)]
         debug!("avg_time: {avg_time:?}");
@@ -97,31 +157,90 @@ impl Buffer {
         self.current_fps = current_fps;
     }
 
-    fn calculate_target_fps(&mut self) {
-        let target_fpses = match &self.target_fps_config {
+    pub fn target_fpses(&self) -> Vec<u32> {
+        match &self.target_fps_config {
             TargetFps::Value(t) => vec![*t],
             TargetFps::Array(arr) => arr.clone(),
-        };
+            TargetFps::Auto => {
+                // 加入屏幕支持的刷新率
+                let mut target_fpses = AUTO_TARGET_FPS.to_vec();
+                target_fpses.extend_from_slice(&self.display.modes);
+                target_fpses.sort_unstable();
+                target_fpses.dedup();
+                target_fpses
+            }
+        }
+    }
+
+    fn calculate_target_fps(&mut self) {
+        let mut target_fpses = self.target_fpses();
 
-        if self.current_fps < (target_fpses[0].saturating_sub(10).max(10)).into() {
-            self.target_fps = None;
+        // 温控降级, 非全屏窗口或者屏幕刷新率以上的目标帧率不可能达到, 至少保留最低的目标帧率
+        let cap = [self.fps_cap, self.window_cap, self.display.refresh_rate]
+            .into_iter()
+            .flatten()
+            .min();
+        if let Some(cap) = cap {
+            let cap = cap.max(target_fpses[0]);
+            target_fpses.retain(|t| *t <= cap);
+        }
+
+        let candidate = self.match_target_fps(&target_fpses);
+        if candidate == self.target_fps {
+            self.target_candidate = None;
             return;
         }
 
+        // 第一次匹配或者当前目标帧率已经超出上限时立即生效, 之后的变化需要持续一段时间
+        let capped = self
+            .target_fps
+            .is_some_and(|t| target_fpses.last().is_some_and(|max| t > *max));
+        let dwelled = (self.target_fps.is_none() && candidate.is_some())
+            || capped
+            || match self.target_candidate {
+                Some((c, since)) if c == candidate => since.elapsed() >= self.timing.target_dwell(),
+                _ => {
+                    self.target_candidate = Some((candidate, Instant::now()));
+                    false
+                }
+            };
+
+        if dwelled {
+            #[cfg(debug_assertions)]
+            debug!(
+                "Matched target_fps: current: {:.2} target_fps: {candidate:?}",
+                self.current_fps
+            );
+
+            self.target_fps = candidate;
+            self.target_candidate = None;
+        }
+    }
+
+    fn match_target_fps(&self, target_fpses: &[u32]) -> Option<u32> {
+        if self.current_fps
+            < (target_fpses[0]
+                .saturating_sub(self.timing.fps_tolerance)
+                .max(10))
+            .into()
+        {
+            return None;
+        }
+
         for target_fps in target_fpses.iter().copied() {
-            if self.current_fps <= f64::from(target_fps) + 3.0 {
-                #[cfg(debug_assertions)]
-                debug!(
-                    "Matched target_fps: current: {:.2} target_fps: {target_fps}",
-                    self.current_fps
-                );
-
-                self.target_fps = Some(target_fps);
-                return;
+            // 离开当前目标帧率需要额外超出hysteresis, 避免在两个目标之间来回跳
+            let margin = if self.target_fps == Some(target_fps) {
+                self.timing.fps_margin + self.timing.fps_hysteresis
+            } else {
+                self.timing.fps_margin
+            };
+
+            if self.current_fps <= f64::from(target_fps) + margin {
+                return Some(target_fps);
             }
         }
 
-        self.target_fps = target_fpses.last().copied();
+        target_fpses.last().copied()
     }
 
     pub fn calculate_deviation(&mut self) {
@@ -130,18 +249,8 @@ impl Buffer {
         }
 
         if let Some(target_fps) = self.target_fps {
-            let avg = self.avg_time * target_fps;
-
-            let standard_deviation: f64 = {
-                let total: f64 = self
-                    .frametimes
-                    .iter()
-                    .copied()
-                    .map(|f| f.as_secs_f64() * f64::from(target_fps)) // normalization
-                    .map(|f| (f - avg.as_secs_f64()).abs())
-                    .sum();
-                total / self.frametimes.len() as f64
-            };
+            // normalization
+            let standard_deviation = (self.frametimes.std_dev() * target_fps).as_secs_f64();
 
             #[cfg(debug_assertions)]
             debug!("standard deviation: {standard_deviation:.2}");
@@ -150,3 +259,143 @@ impl Buffer {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SECOND: Duration = Duration::from_secs(1);
+
+    // 模拟经过elapsed后以fps帧率重新匹配目标帧率
+    fn step(buffer: &mut Buffer, fps: f64, elapsed: Duration) -> Option<u32> {
+        if let Some((candidate, since)) = buffer.target_candidate {
+            buffer.target_candidate = Some((candidate, since.checked_sub(elapsed).unwrap()));
+        }
+
+        buffer.current_fps = fps;
+        buffer.calculate_target_fps();
+        buffer.target_fps
+    }
+
+    fn buffer() -> Buffer {
+        Buffer::new(TargetFps::Array(vec![30, 60, 90, 120]))
+    }
+
+    #[test]
+    fn hover_60() {
+        let mut buffer = buffer();
+
+        for fps in [58.0, 62.0, 63.0, 59.5, 61.0, 63.0, 57.0] {
+            assert_eq!(step(&mut buffer, fps, SECOND), Some(60));
+        }
+    }
+
+    #[test]
+    fn hover_63_68() {
+        let mut buffer = buffer();
+        assert_eq!(step(&mut buffer, 60.0, SECOND), Some(60));
+
+        // 离开当前目标需要超出margin + hysteresis
+        for fps in [63.0, 66.0, 68.0, 64.0, 68.0] {
+            assert_eq!(step(&mut buffer, fps, SECOND), Some(60));
+            assert_eq!(buffer.target_candidate, None);
+        }
+
+        // 在边界两侧来回不会累积停留时间
+        for _ in 0..5 {
+            assert_eq!(step(&mut buffer, 69.0, SECOND), Some(60));
+            assert_eq!(step(&mut buffer, 69.0, SECOND), Some(60));
+            assert_eq!(step(&mut buffer, 66.0, SECOND), Some(60));
+        }
+    }
+
+    #[test]
+    fn dwell() {
+        let mut buffer = buffer();
+        assert_eq!(step(&mut buffer, 60.0, SECOND), Some(60));
+
+        assert_eq!(step(&mut buffer, 75.0, SECOND), Some(60));
+        assert_eq!(step(&mut buffer, 75.0, SECOND), Some(60));
+        assert_eq!(step(&mut buffer, 75.0, SECOND), Some(60));
+        assert_eq!(step(&mut buffer, 75.0, SECOND), Some(90));
+
+        assert_eq!(step(&mut buffer, 28.0, SECOND), Some(90));
+        assert_eq!(
+            step(&mut buffer, 28.0, Duration::from_millis(2900)),
+            Some(90)
+        );
+        assert_eq!(
+            step(&mut buffer, 28.0, Duration::from_millis(100)),
+            Some(30)
+        );
+
+        // 低于最低目标帧率减去容差视为没有目标
+        assert_eq!(step(&mut buffer, 15.0, SECOND), Some(30));
+        assert_eq!(step(&mut buffer, 15.0, SECOND * 3), None);
+    }
+
+    #[test]
+    fn first_match_immediately() {
+        let mut buffer = buffer();
+
+        assert_eq!(step(&mut buffer, 10.0, SECOND), None);
+        assert_eq!(step(&mut buffer, 118.0, Duration::ZERO), Some(120));
+    }
+
+    #[test]
+    fn panel_rate_cap() {
+        let mut buffer = buffer();
+        buffer.display = DisplayInfo {
+            refresh_rate: Some(60),
+            modes: vec![60, 90, 120],
+        };
+
+        for fps in [58.0, 75.0, 90.0, 118.0] {
+            assert_eq!(step(&mut buffer, fps, SECOND * 5), Some(60));
+        }
+
+        // 上限提高时仍然需要停留
+        buffer.display.refresh_rate = Some(90);
+        assert_eq!(step(&mut buffer, 118.0, SECOND), Some(60));
+        assert_eq!(step(&mut buffer, 118.0, SECOND * 3), Some(90));
+        assert_eq!(step(&mut buffer, 118.0, SECOND * 5), Some(90));
+
+        // 刷新率低于所有目标帧率时保留最低的目标帧率, 并且立即生效
+        buffer.display.refresh_rate = Some(24);
+        assert_eq!(step(&mut buffer, 30.0, Duration::ZERO), Some(30));
+        assert_eq!(buffer.target_candidate, None);
+        assert_eq!(step(&mut buffer, 60.0, SECOND * 5), Some(30));
+    }
+
+    #[test]
+    fn auto_targets_follow_display() {
+        let mut buffer = Buffer::new(TargetFps::Auto);
+        buffer.display = DisplayInfo {
+            refresh_rate: Some(165),
+            modes: vec![60, 120, 165],
+        };
+
+        assert_eq!(buffer.target_fpses(), [30, 45, 60, 90, 120, 144, 165]);
+        assert_eq!(step(&mut buffer, 164.0, SECOND), Some(165));
+
+        buffer.display.refresh_rate = Some(120);
+        assert_eq!(step(&mut buffer, 164.0, Duration::ZERO), Some(120));
+    }
+
+    #[test]
+    fn fps_cap_immediately() {
+        let mut buffer = buffer();
+        assert_eq!(step(&mut buffer, 118.0, SECOND), Some(120));
+
+        // 温控上限降低
+        buffer.fps_cap = Some(60);
+        assert_eq!(step(&mut buffer, 118.0, Duration::ZERO), Some(60));
+
+        // 上限内的降档仍然需要停留
+        buffer.fps_cap = None;
+        assert_eq!(step(&mut buffer, 118.0, SECOND * 3), Some(60));
+        assert_eq!(step(&mut buffer, 118.0, SECOND * 3), Some(120));
+        assert_eq!(step(&mut buffer, 88.0, Duration::ZERO), Some(120));
+        assert_eq!(step(&mut buffer, 88.0, SECOND * 3), Some(90));
+    }
+}
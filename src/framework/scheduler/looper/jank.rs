@@ -0,0 +1,177 @@
+/* Copyright 2023 shadow3aaa@gitbub.com
+*
+*  Licensed under the Apache License, Version 2.0 (the "License");
+*  you may not use this file except in compliance with the License.
+*  You may obtain a copy of the License at
+*
+*      http://www.apache.org/licenses/LICENSE-2.0
+*
+*  Unless required by applicable law or agreed to in writing, software
+*  distributed under the License is distributed on an "AS IS" BASIS,
+*  WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+*  See the License for the specific language governing permissions and
+*  limitations under the License. */
+use std::time::Duration;
+
+use super::policy::JankEvent;
+
+// PerfDog的卡顿定义: 帧时间超过前三帧平均的两倍, 且超过84ms(Jank) / 125ms(BigJank)
+const JANK: Duration = Duration::from_millis(84);
+const BIG_JANK: Duration = Duration::from_millis(125);
+
+#[derive(Debug, Default)]
+pub struct JankClassifier {
+    recent: [Duration; 3],
+    len: usize,
+}
+
+impl JankClassifier {
+    pub fn classify(&mut self, frame: Duration) -> JankEvent {
+        let event = if self.len < self.recent.len() {
+            JankEvent::None
+        } else {
+            let threshold = self.recent.iter().sum::<Duration>() * 2 / 3;
+
+            if frame > threshold && frame > BIG_JANK {
+                JankEvent::BigJank
+            } else if frame > threshold && frame > JANK {
+                JankEvent::Jank
+            } else {
+                JankEvent::None
+            }
+        };
+
+        self.recent.rotate_right(1);
+        self.recent[0] = frame;
+        self.len = (self.len + 1).min(self.recent.len());
+
+        event
+    }
+}
+
+// 一次游戏会话的卡顿统计, 卡顿率为卡顿帧时间占总时间的比例
+#[derive(Debug, Default)]
+pub struct StutterStats {
+    frames: u64,
+    jank: u64,
+    big_jank: u64,
+    jank_time: Duration,
+    total_time: Duration,
+}
+
+impl StutterStats {
+    pub fn record(&mut self, frame: Duration, event: JankEvent) {
+        self.frames += 1;
+        self.total_time += frame;
+
+        match event {
+            JankEvent::BigJank => {
+                self.big_jank += 1;
+                self.jank_time += frame;
+            }
+            JankEvent::Jank => {
+                self.jank += 1;
+                self.jank_time += frame;
+            }
+            JankEvent::None => (),
+        }
+    }
+
+    pub const fn frames(&self) -> u64 {
+        self.frames
+    }
+
+    pub fn stutter_rate(&self) -> f64 {
+        if self.total_time.is_zero() {
+            0.0
+        } else {
+            self.jank_time.as_secs_f64() / self.total_time.as_secs_f64()
+        }
+    }
+}
+
+impl ToString for StutterStats {
+    fn to_string(&self) -> String {
+        format!(
+            "frames: {} jank: {} big_jank: {} stutter: {:.2}%",
+            self.frames,
+            self.jank,
+            self.big_jank,
+            self.stutter_rate() * 100.0
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const fn ms(ms: u64) -> Duration {
+        Duration::from_millis(ms)
+    }
+
+    // 用三帧frame预热后判定next
+    fn classify_after(frame: Duration, next: Duration) -> JankEvent {
+        let mut classifier = JankClassifier::default();
+        for _ in 0..3 {
+            classifier.classify(frame);
+        }
+
+        classifier.classify(next)
+    }
+
+    #[test]
+    fn first_three_frames() {
+        let mut classifier = JankClassifier::default();
+
+        for _ in 0..3 {
+            assert_eq!(classifier.classify(ms(500)), JankEvent::None);
+        }
+        assert_eq!(classifier.classify(ms(2000)), JankEvent::BigJank);
+    }
+
+    #[test]
+    fn twice_the_mean() {
+        assert_eq!(classify_after(ms(50), ms(100)), JankEvent::None);
+        assert_eq!(classify_after(ms(50), ms(101)), JankEvent::Jank);
+
+        assert_eq!(classify_after(ms(70), ms(140)), JankEvent::None);
+        assert_eq!(classify_after(ms(70), ms(141)), JankEvent::BigJank);
+
+        // 平均值按最近三帧计算
+        let mut classifier = JankClassifier::default();
+        for frame in [ms(30), ms(60), ms(90)] {
+            classifier.classify(frame);
+        }
+        assert_eq!(classifier.classify(ms(120)), JankEvent::None);
+        assert_eq!(classifier.classify(ms(181)), JankEvent::BigJank);
+    }
+
+    #[test]
+    fn absolute_boundaries() {
+        assert_eq!(classify_after(ms(10), ms(84)), JankEvent::None);
+        assert_eq!(classify_after(ms(10), ms(85)), JankEvent::Jank);
+        assert_eq!(classify_after(ms(10), ms(125)), JankEvent::Jank);
+        assert_eq!(classify_after(ms(10), ms(126)), JankEvent::BigJank);
+    }
+
+    #[test]
+    fn stutter_rate() {
+        let mut stats = StutterStats::default();
+        assert!(stats.stutter_rate().abs() < f64::EPSILON);
+
+        for _ in 0..8 {
+            stats.record(ms(100), JankEvent::None);
+        }
+        stats.record(ms(100), JankEvent::Jank);
+        stats.record(ms(300), JankEvent::BigJank);
+
+        assert_eq!(stats.frames(), 10);
+        assert_eq!((stats.jank, stats.big_jank), (1, 1));
+        assert!((stats.stutter_rate() - 400.0 / 1200.0).abs() < 1e-9);
+        assert_eq!(
+            stats.to_string(),
+            "frames: 10 jank: 1 big_jank: 1 stutter: 33.33%"
+        );
+    }
+}
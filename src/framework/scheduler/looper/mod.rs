@@ -11,28 +11,40 @@
 *  WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
 *  See the License for the specific language governing permissions and
 *  limitations under the License. */
+mod aggregate;
 mod buffer;
+mod jank;
+mod percentile;
 mod policy;
+mod rolling;
+mod scene;
+mod state;
 mod utils;
 
 use std::{
     collections::HashMap,
+    mem,
     sync::mpsc::{Receiver, RecvTimeoutError},
     time::{Duration, Instant},
 };
 
-use super::{topapp::TimedWatcher, BinderMessage, FasData};
+use super::{
+    auto_mode::AutoMode, display::DisplayWatcher, producer::Producer, screen::ScreenMonitor,
+    thermal::ThermalMonitor, topapp::TopAppWatcher, window::WindowWatcher, BinderMessage, FasData,
+};
 use crate::framework::{
-    config::Config,
+    config::{Config, TimingConfig},
     error::{Error, Result},
     node::{Mode, Node},
     PerformanceController,
 };
 
 use buffer::Buffer;
+use jank::StutterStats;
 use policy::{JankEvent, NormalEvent};
+use scene::Scene;
+use state::{Event, State, StateMachine};
 
-pub type Producer = (i64, i32); // buffer, pid
 pub type Buffers = HashMap<Producer, Buffer>; // Process, (jank_scale, total_jank_time_ns)
 
 pub struct Looper<P: PerformanceController> {
@@ -40,14 +52,28 @@ pub struct Looper<P: PerformanceController> {
     config: Config,
     node: Node,
     mode: Mode,
+    global_mode: Mode,
+    game_mode: Option<Mode>,
+    auto_mode: AutoMode,
     controller: P,
-    topapp_checker: TimedWatcher,
+    topapp_checker: TopAppWatcher,
+    thermal: ThermalMonitor,
+    display: DisplayWatcher,
+    window: WindowWatcher,
+    screen: ScreenMonitor,
     buffers: Buffers,
-    start: bool,
-    start_delayed: bool,
-    delay_timer: Instant,
+    state: StateMachine,
     last_control: Instant,
     limit_delay: Duration,
+    status_timer: Instant,
+    fps_cap: Option<u32>,
+    game: Option<String>,
+    timing: TimingConfig,
+    timing_timer: Instant,
+    stutter: StutterStats,
+    scene: Scene,
+    scene_timer: Instant,
+    gc_timer: Instant,
 }
 
 impl<P: PerformanceController> Looper<P> {
@@ -57,31 +83,83 @@ impl<P: PerformanceController> Looper<P> {
             config,
             node,
             mode: Mode::Balance,
+            global_mode: Mode::Balance,
+            game_mode: None,
+            auto_mode: AutoMode::new(),
             controller,
-            topapp_checker: TimedWatcher::new(),
+            topapp_checker: TopAppWatcher::new(),
+            thermal: ThermalMonitor::new(),
+            display: DisplayWatcher::new(),
+            window: WindowWatcher::new(),
+            screen: ScreenMonitor::new(),
             buffers: Buffers::new(),
-            start: false,
-            start_delayed: false,
-            delay_timer: Instant::now(),
+            state: StateMachine::new(),
             last_control: Instant::now(),
             limit_delay: Duration::from_secs(1),
+            status_timer: Instant::now(),
+            fps_cap: None,
+            game: None,
+            timing: TimingConfig::default(),
+            timing_timer: Instant::now(),
+            stutter: StutterStats::default(),
+            scene: Scene::Normal,
+            scene_timer: Instant::now(),
+            gc_timer: Instant::now(),
         }
     }
 
     pub fn enter_loop(&mut self) -> Result<()> {
+        self.publish_status();
+
         loop {
-            let new_mode = self.node.get_mode()?;
-            if self.mode != new_mode && self.start_delayed {
-                self.controller.init_game(new_mode, &self.config)?;
+            self.global_mode = self.auto_mode.update(&self.config, &mut self.node)?;
+            let new_mode = self.game_mode.unwrap_or(self.global_mode);
+            if self.mode != new_mode {
+                if self.state.state() == State::Active {
+                    self.controller.init_game(new_mode, &self.config)?;
+                }
                 self.mode = new_mode;
+                self.refresh_timing();
+            }
+
+            // 应用配置的热重载
+            if self.timing_timer.elapsed() > Duration::from_secs(1) {
+                self.refresh_timing();
+            }
+
+            if self.state.state() != State::Idle {
+                self.update_screen()?;
+                self.update_display();
+                self.update_window()?;
+            }
+
+            if self.state.state() == State::Warmup && self.state.elapsed() > self.timing.warmup() {
+                self.handle_event(Event::WarmupElapsed)?;
+            }
+
+            if self.state.state() == State::Active {
+                if let Some(ceiling) = self.thermal.update(&self.config, self.mode) {
+                    self.controller.set_ceiling(ceiling);
+                }
+
+                if let Some(step) = self.thermal.take_fps_step() {
+                    self.apply_fps_step(step);
+                }
+
+                if self.scene_timer.elapsed() > Duration::from_secs(1) {
+                    self.update_scene()?;
+                }
+
+                if self.status_timer.elapsed() > Duration::from_secs(1) {
+                    self.publish_status();
+                }
+            }
+
+            if self.gc_timer.elapsed() > Duration::from_secs(1) {
+                self.gc_buffers()?;
             }
 
-            let target_fps = self
-                .buffers
-                .values()
-                .filter(|b| b.last_update.elapsed() < Duration::from_secs(1))
-                .filter_map(|b| b.target_fps)
-                .max(); // 只处理目标fps最大的buffer
+            let target_fps = self.control_target_fps();
 
             if let Some(message) = self.recv_message(target_fps)? {
                 match message {
@@ -92,17 +170,22 @@ impl<P: PerformanceController> Looper<P> {
                     BinderMessage::RemoveBuffer(k) => {
                         self.buffers.remove(&k);
                     }
+                    BinderMessage::ModeChanged => self.node.refresh_mode()?,
+                    BinderMessage::ConfigReloaded => self.refresh_timing(),
                 }
-            } else {
+            } else if self.state.state() != State::Idle {
                 self.do_jank_policy(target_fps)?;
             }
         }
     }
 
     fn recv_message(&mut self, target_fps: Option<u32>) -> Result<Option<BinderMessage>> {
-        let timeout = target_fps.map_or(Duration::from_secs(1), |t| Duration::from_secs(2) / t);
-        let timeout_error =
-            target_fps.map_or(Duration::from_secs(5), |t| Duration::from_secs(10) / t);
+        if self.state.state() == State::Idle {
+            return self.recv_idle_message();
+        }
+
+        let timeout = self.timing.recv_timeout(target_fps);
+        let timeout_error = self.timing.timeout(target_fps);
 
         match self.rx.recv_timeout(timeout) {
             Ok(m) => Ok(Some(m)),
@@ -113,8 +196,8 @@ impl<P: PerformanceController> Looper<P> {
 
                 self.retain_topapp()?;
 
-                if self.start_delayed && self.latest_update_elapsed() > timeout_error {
-                    self.disable_fas()?;
+                if self.latest_update_elapsed() > timeout_error {
+                    self.handle_event(Event::FrameTimeout)?;
                 }
 
                 Ok(None)
@@ -122,24 +205,58 @@ impl<P: PerformanceController> Looper<P> {
         }
     }
 
+    // 没有游戏在运行, 阻塞到有帧数据, mode节点写入或者配置重载
+    fn recv_idle_message(&self) -> Result<Option<BinderMessage>> {
+        let Some(timeout) = AutoMode::idle_timeout(&self.config) else {
+            return self
+                .rx
+                .recv()
+                .map(Some)
+                .map_err(|_| Error::Other("Binder Server Disconnected"));
+        };
+
+        match self.rx.recv_timeout(timeout) {
+            Ok(m) => Ok(Some(m)),
+            Err(RecvTimeoutError::Timeout) => Ok(None),
+            Err(RecvTimeoutError::Disconnected) => Err(Error::Other("Binder Server Disconnected")),
+        }
+    }
+
     fn consume_data(&mut self, data: &FasData) -> Result<()> {
-        self.buffer_update(data);
-        self.retain_topapp()
+        // 熄屏后客户端可能还会继续提交一段时间的帧
+        self.update_screen()?;
+        if !self.screen.is_on() {
+            return Ok(());
+        }
+
+        self.buffer_update(data)?;
+        self.retain_topapp()?;
+
+        if !self.buffers.is_empty() && !self.window_suspended() {
+            self.handle_event(Event::Frame)?;
+        }
+
+        Ok(())
     }
 
     fn do_normal_policy(&mut self, target_fps: Option<u32>) -> Result<()> {
-        if !self.start_delayed {
+        if self.state.state() != State::Active {
             return Ok(());
         }
 
-        let Some(event) = self
-            .buffers
-            .values_mut()
-            .filter(|buffer| buffer.target_fps == target_fps)
-            .map(|buffer| buffer.normal_event(&self.config, self.mode))
-            .max()
-        else {
-            self.disable_fas()?;
+        let selected = self.selected_buffers();
+
+        let mut perfdog_jank = JankEvent::None;
+        let mut events = Vec::with_capacity(selected.len());
+        for (producer, weight) in selected {
+            if let Some(buffer) = self.buffers.get_mut(&producer) {
+                perfdog_jank = perfdog_jank.max(mem::take(&mut buffer.last_jank));
+                events.push((buffer.normal_event(&self.config, self.mode), weight));
+            }
+        }
+
+        let Some(event) = self.aggregate(&events) else {
+            self.handle_event(Event::FrameTimeout)?;
             return Ok(());
         };
 
@@ -147,18 +264,26 @@ impl<P: PerformanceController> Looper<P> {
             return Ok(());
         };
 
+        // PerfDog定义的卡顿作为额外的触发条件
+        if perfdog_jank != JankEvent::None && self.config.mode_config(self.mode).perfdog_jank {
+            return self.apply_jank_event(perfdog_jank, target_fps);
+        }
+
+        let control_interval = Duration::from_secs(self.timing.control_interval.into());
+        let limit_delay = Duration::from_secs(self.timing.limit_delay.into());
+
         match event {
             NormalEvent::Release => {
-                if self.last_control.elapsed() * target_fps > Duration::from_secs(1) {
+                if self.last_control.elapsed() * target_fps > control_interval {
                     self.last_control = Instant::now();
-                    self.limit_delay = Duration::from_secs(1);
+                    self.limit_delay = limit_delay;
                     self.controller.release(self.mode, &self.config)?;
                 }
             }
             NormalEvent::Restrictable => {
                 if self.last_control.elapsed() * target_fps > self.limit_delay {
                     self.last_control = Instant::now();
-                    self.limit_delay = Duration::from_secs(1);
+                    self.limit_delay = limit_delay;
                     self.controller.limit(self.mode, &self.config)?;
                 }
             }
@@ -169,20 +294,23 @@ impl<P: PerformanceController> Looper<P> {
     }
 
     fn do_jank_policy(&mut self, target_fps: Option<u32>) -> Result<()> {
-        if !self.start_delayed {
+        if self.state.state() != State::Active {
             return Ok(());
         }
 
         self.buffers.values_mut().for_each(Buffer::frame_prepare);
 
-        let Some(event) = self
-            .buffers
-            .values_mut()
-            .filter(|buffer| buffer.target_fps == target_fps)
-            .map(|buffer| buffer.jank_event(&self.config, self.mode))
-            .max()
-        else {
-            self.disable_fas()?;
+        let selected = self.selected_buffers();
+
+        let mut events = Vec::with_capacity(selected.len());
+        for (producer, weight) in selected {
+            if let Some(buffer) = self.buffers.get_mut(&producer) {
+                events.push((buffer.jank_event(&self.config, self.mode), weight));
+            }
+        }
+
+        let Some(event) = self.aggregate(&events) else {
+            self.handle_event(Event::FrameTimeout)?;
             return Ok(());
         };
 
@@ -190,18 +318,31 @@ impl<P: PerformanceController> Looper<P> {
             return Ok(());
         };
 
+        self.apply_jank_event(event, target_fps)
+    }
+
+    fn apply_jank_event(&mut self, event: JankEvent, target_fps: u32) -> Result<()> {
+        // 过热降频时无法达到的帧时间不再追加最大提升
+        let event = if self.thermal.throttled() {
+            event.min(JankEvent::Jank)
+        } else {
+            event
+        };
+
+        let control_interval = Duration::from_secs(self.timing.control_interval.into());
+
         match event {
             JankEvent::BigJank => {
-                if self.last_control.elapsed() * target_fps > Duration::from_secs(1) {
+                if self.last_control.elapsed() * target_fps > control_interval {
                     self.last_control = Instant::now();
-                    self.limit_delay = Duration::from_secs(5);
+                    self.limit_delay = Duration::from_secs(self.timing.big_jank_limit_delay.into());
                     self.controller.release_max(self.mode, &self.config)?;
                 }
             }
             JankEvent::Jank => {
-                if self.last_control.elapsed() * target_fps > Duration::from_secs(1) {
+                if self.last_control.elapsed() * target_fps > control_interval {
                     self.last_control = Instant::now();
-                    self.limit_delay = Duration::from_secs(3);
+                    self.limit_delay = Duration::from_secs(self.timing.jank_limit_delay.into());
                     self.controller.release(self.mode, &self.config)?;
                 }
             }
@@ -211,3 +352,108 @@ impl<P: PerformanceController> Looper<P> {
         Ok(())
     }
 }
+
+#[cfg(test)]
+pub mod tests {
+    use std::{
+        cell::{Cell, RefCell},
+        sync::mpsc,
+    };
+
+    use super::*;
+    use crate::{framework::config::TargetFps, misc::Fixture};
+
+    // 记录looper对控制器的调用
+    #[derive(Default)]
+    pub struct Recorder {
+        pub calls: RefCell<Vec<&'static str>>,
+        pub usage: Cell<Option<f64>>,
+    }
+
+    impl Recorder {
+        pub fn take(&self) -> Vec<&'static str> {
+            self.calls.take()
+        }
+    }
+
+    impl PerformanceController for Recorder {
+        fn limit(&self, _m: Mode, _c: &Config) -> Result<()> {
+            self.calls.borrow_mut().push("limit");
+            Ok(())
+        }
+
+        fn release(&self, _m: Mode, _c: &Config) -> Result<()> {
+            self.calls.borrow_mut().push("release");
+            Ok(())
+        }
+
+        fn release_max(&self, _m: Mode, _c: &Config) -> Result<()> {
+            self.calls.borrow_mut().push("release_max");
+            Ok(())
+        }
+
+        fn init_game(&self, _m: Mode, _c: &Config) -> Result<()> {
+            self.calls.borrow_mut().push("init_game");
+            Ok(())
+        }
+
+        fn init_default(&self, _m: Mode, _c: &Config) -> Result<()> {
+            self.calls.borrow_mut().push("init_default");
+            Ok(())
+        }
+
+        fn usage(&self) -> Option<f64> {
+            self.usage.get()
+        }
+    }
+
+    // fixture中只有TOP_PID在前台
+    pub const TOP_PID: i32 = 1000;
+
+    // 节点写到fixture中, 不接收binder消息
+    pub fn looper(fixture: &Fixture) -> Looper<Recorder> {
+        let (_, rx) = mpsc::channel();
+        let config = Config::new("module/games.toml", "module/games.toml").unwrap();
+        let node = Node::init_at(fixture.path().join("fas_rs")).unwrap();
+        fixture.write("dev/cpuset/top-app/cgroup.procs", format!("{TOP_PID}\n"));
+
+        let mut looper = Looper::new(rx, config, node, Recorder::default());
+        looper.topapp_checker = TopAppWatcher::from_root(fixture.path());
+        looper
+    }
+
+    pub fn frame(frametime: Duration) -> FasData {
+        FasData {
+            producer: Producer::new(1, 1, TOP_PID),
+            target_fps: TargetFps::Array(vec![30, 60]),
+            pkg: "com.example.game".into(),
+            frametime,
+            cpu: 0,
+        }
+    }
+
+    #[test]
+    fn screen_off_suspends() {
+        let fixture = Fixture::new("looper-screen");
+        let mut looper = looper(&fixture);
+
+        looper
+            .consume_data(&frame(Duration::from_millis(16)))
+            .unwrap();
+        assert_eq!(looper.state.state(), State::Warmup);
+        looper.handle_event(Event::WarmupElapsed).unwrap();
+        assert_eq!(looper.controller.take(), ["init_game"]);
+
+        looper.handle_event(Event::ScreenOff).unwrap();
+        assert_eq!(looper.state.state(), State::Suspended);
+        assert_eq!(looper.controller.take(), ["init_default"]);
+        assert_eq!(fixture.read("fas_rs/state"), "suspended");
+
+        // 亮屏后的下一帧重新进入预热
+        looper
+            .consume_data(&frame(Duration::from_millis(16)))
+            .unwrap();
+        assert_eq!(looper.state.state(), State::Warmup);
+        assert!(looper.controller.take().is_empty());
+    }
+}
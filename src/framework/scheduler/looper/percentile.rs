@@ -0,0 +1,120 @@
+/* Copyright 2023 shadow3aaa@gitbub.com
+*
+*  Licensed under the Apache License, Version 2.0 (the "License");
+*  you may not use this file except in compliance with the License.
+*  You may obtain a copy of the License at
+*
+*      http://www.apache.org/licenses/LICENSE-2.0
+*
+*  Unless required by applicable law or agreed to in writing, software
+*  distributed under the License is distributed on an "AS IS" BASIS,
+*  WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+*  See the License for the specific language governing permissions and
+*  limitations under the License. */
+use std::time::Duration;
+
+// 对数分桶, 覆盖1ms ~ 10s, 相邻桶相差1%
+const MIN_NANOS: f64 = 1_000_000.0;
+const GROWTH: f64 = 1.01;
+const BUCKETS: usize = 927;
+
+#[derive(Debug)]
+pub struct Histogram {
+    counts: Vec<u32>,
+    total: u32,
+}
+
+impl Histogram {
+    pub fn new() -> Self {
+        Self {
+            counts: vec![0; BUCKETS],
+            total: 0,
+        }
+    }
+
+    pub fn add(&mut self, frame: Duration) {
+        self.counts[Self::index(frame)] += 1;
+        self.total += 1;
+    }
+
+    pub fn remove(&mut self, frame: Duration) {
+        let count = &mut self.counts[Self::index(frame)];
+        if *count > 0 {
+            *count -= 1;
+            self.total -= 1;
+        }
+    }
+
+    // 近似值, 相对误差不超过1%
+    pub fn percentile(&self, q: f64) -> Duration {
+        let rank = ((f64::from(self.total) * q).ceil() as u32).max(1);
+
+        let mut acc = 0;
+        for (index, count) in self.counts.iter().enumerate() {
+            acc += count;
+            if acc >= rank {
+                return Self::value(index);
+            }
+        }
+
+        Duration::ZERO
+    }
+
+    fn index(frame: Duration) -> usize {
+        let nanos = frame.as_nanos() as f64;
+        if nanos <= MIN_NANOS {
+            return 0;
+        }
+
+        ((nanos / MIN_NANOS).log(GROWTH) as usize).min(BUCKETS - 1)
+    }
+
+    fn value(index: usize) -> Duration {
+        Duration::from_nanos((MIN_NANOS * GROWTH.powf(index as f64 + 0.5)) as u64)
+    }
+}
+
+// 1% / 0.1% low为P99 / P99.9帧时间对应的帧率
+#[derive(Debug, Clone, Copy)]
+pub struct Percentiles {
+    pub p50: Duration,
+    pub p90: Duration,
+    pub p99: Duration,
+    pub low_1: f64,
+    pub low_01: f64,
+}
+
+impl Percentiles {
+    pub fn new(histogram: &Histogram) -> Self {
+        let fps = |d: Duration| {
+            if d.is_zero() {
+                0.0
+            } else {
+                1.0 / d.as_secs_f64()
+            }
+        };
+
+        let p99 = histogram.percentile(0.99);
+
+        Self {
+            p50: histogram.percentile(0.5),
+            p90: histogram.percentile(0.9),
+            p99,
+            low_1: fps(p99),
+            low_01: fps(histogram.percentile(0.999)),
+        }
+    }
+}
+
+impl ToString for Percentiles {
+    fn to_string(&self) -> String {
+        format!(
+            "p50: {:.2}ms p90: {:.2}ms p99: {:.2}ms 1%_low: {:.2} 0.1%_low: {:.2}",
+            self.p50.as_secs_f64() * 1000.0,
+            self.p90.as_secs_f64() * 1000.0,
+            self.p99.as_secs_f64() * 1000.0,
+            self.low_1,
+            self.low_01
+        )
+    }
+}
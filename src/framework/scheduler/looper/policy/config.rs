@@ -16,19 +16,24 @@ use std::time::Duration;
 use super::super::Buffer;
 use crate::framework::{node::Mode, Config};
 
+// sqrt(2/π)
+const MAD_PER_STD_DEV: f64 = 0.797_884_560_802_865_4;
+
 #[derive(Debug, Clone, Copy)]
 pub struct PolicyConfig {
     pub acc_dur: Duration,
     pub scale: f64,
     pub jank_scale: f64,
     pub big_jank_scale: f64,
+    pub p99_jank_scale: Option<f64>,
 }
 
 impl PolicyConfig {
     pub fn new(config: &Config, mode: Mode, buffer: &Buffer) -> Self {
         let target_fps = buffer.target_fps.unwrap_or(10);
         let target_fps = f64::from(target_fps);
-        let acc_dur = 1.0 / buffer.deviation;
+        // acc_dur原本按平均绝对偏差调参, 正态分布下MAD = sqrt(2/π)·σ
+        let acc_dur = 1.0 / (buffer.deviation * MAD_PER_STD_DEV);
         let acc_dur = acc_dur.clamp(1.0, 10.0);
 
         let scale = config.mode_config(mode).scale;
@@ -45,6 +50,54 @@ impl PolicyConfig {
             scale,
             jank_scale,
             big_jank_scale,
+            p99_jank_scale: config.mode_config(mode).p99_jank_scale,
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::framework::config::TargetFps;
+
+    #[test]
+    fn normal_mad() {
+        // Irwin-Hall近似正态分布
+        let mut state = 42_u64;
+        let mut uniform = || {
+            state ^= state << 13;
+            state ^= state >> 7;
+            state ^= state << 17;
+            (state % 1_000_000) as f64 / 1_000_000.0
+        };
+        let samples: Vec<f64> = (0..20000)
+            .map(|_| (0..12).map(|_| uniform()).sum::<f64>() - 6.0)
+            .collect();
+
+        let len = samples.len() as f64;
+        let mean = samples.iter().sum::<f64>() / len;
+        let mad = samples.iter().map(|s| (s - mean).abs()).sum::<f64>() / len;
+        let std_dev = (samples.iter().map(|s| (s - mean).powi(2)).sum::<f64>() / len).sqrt();
+
+        assert!((std_dev * MAD_PER_STD_DEV - mad).abs() / mad < 0.02);
+    }
+
+    #[test]
+    fn acc_dur() {
+        let config = Config::new("module/games.toml", "module/games.toml").unwrap();
+        let mut buffer = Buffer::new(TargetFps::Value(60));
+        buffer.target_fps = Some(60);
+
+        buffer.deviation = 0.25;
+        let policy = PolicyConfig::new(&config, Mode::Balance, &buffer);
+        assert!((policy.acc_dur.as_secs_f64() - 1.0 / (0.25 * MAD_PER_STD_DEV)).abs() < 1e-6);
+
+        buffer.deviation = 0.01;
+        let policy = PolicyConfig::new(&config, Mode::Balance, &buffer);
+        assert_eq!(policy.acc_dur, Duration::from_secs(10));
+
+        buffer.deviation = 5.0;
+        let policy = PolicyConfig::new(&config, Mode::Balance, &buffer);
+        assert_eq!(policy.acc_dur, Duration::from_secs(1));
+    }
+}
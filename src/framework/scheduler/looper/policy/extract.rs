@@ -28,6 +28,9 @@ impl PolicyData {
 
         let frames: Vec<_> = buffer.frametimes.iter().copied().take(5).collect();
         let len = frames.len();
+        if len == 0 {
+            return None;
+        }
         let frame = frames.into_iter().sum::<Duration>() / len as u32;
 
         let normalized_avg_frame = buffer.avg_time * target_fps;
@@ -32,8 +32,9 @@ pub enum NormalEvent {
     Release,
 }
 
-#[derive(Debug, PartialEq, Eq, Ord, PartialOrd, Copy, Clone)]
+#[derive(Debug, PartialEq, Eq, Ord, PartialOrd, Copy, Clone, Default)]
 pub enum JankEvent {
+    #[default]
     None,
     Jank,
     BigJank,
@@ -41,6 +42,10 @@ pub enum JankEvent {
 
 impl Buffer {
     pub fn normal_event(&mut self, config: &Config, mode: Mode) -> NormalEvent {
+        if self.rewarming() {
+            return NormalEvent::None;
+        }
+
         let config = PolicyConfig::new(config, mode, self);
         let Some(policy_data) = PolicyData::extract(self) else {
             return NormalEvent::None;
@@ -56,6 +61,11 @@ impl Buffer {
     }
 
     pub fn jank_event(&mut self, config: &Config, mode: Mode) -> JankEvent {
+        // 暂停不是卡顿, 不追加提升
+        if self.rewarming() || self.pausing() {
+            return JankEvent::None;
+        }
+
         let config = PolicyConfig::new(config, mode, self);
         let Some(policy_data) = PolicyData::extract(self) else {
             return JankEvent::None;
@@ -82,6 +92,14 @@ impl Buffer {
             #[cfg(debug_assertions)]
             debug!("JANK: unit jank");
 
+            NormalEvent::Release
+        } else if config.p99_jank_scale.is_some_and(|scale| {
+            let p99 = self.frametimes.percentile(0.99) * policy_data.target_fps;
+            p99.as_secs_f64() - 1.0 >= scale
+        }) {
+            #[cfg(debug_assertions)]
+            debug!("JANK: p99 jank");
+
             NormalEvent::Release
         } else {
             #[cfg(debug_assertions)]
@@ -0,0 +1,199 @@
+/* Copyright 2023 shadow3aaa@gitbub.com
+*
+*  Licensed under the Apache License, Version 2.0 (the "License");
+*  you may not use this file except in compliance with the License.
+*  You may obtain a copy of the License at
+*
+*      http://www.apache.org/licenses/LICENSE-2.0
+*
+*  Unless required by applicable law or agreed to in writing, software
+*  distributed under the License is distributed on an "AS IS" BASIS,
+*  WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+*  See the License for the specific language governing permissions and
+*  limitations under the License. */
+use std::{collections::VecDeque, time::Duration};
+
+use super::percentile::{Histogram, Percentiles};
+
+// 按时间淘汰的帧时间窗口, 维护和与平方和(纳秒, 整数运算无累积误差), 每次更新O(1)
+#[derive(Debug)]
+pub struct RollingWindow {
+    frames: VecDeque<Duration>,
+    sum: u128,
+    sum_sq: u128,
+    histogram: Histogram,
+}
+
+impl RollingWindow {
+    pub fn new() -> Self {
+        Self {
+            frames: VecDeque::new(),
+            sum: 0,
+            sum_sq: 0,
+            histogram: Histogram::new(),
+        }
+    }
+
+    // 最新的帧在前, 淘汰到总时长不超过window(至少保留一帧)
+    pub fn push(&mut self, frame: Duration, window: Duration) {
+        let nanos = frame.as_nanos();
+        self.frames.push_front(frame);
+        self.sum += nanos;
+        self.sum_sq += nanos * nanos;
+        self.histogram.add(frame);
+
+        let window = window.as_nanos();
+        while self.sum > window && self.frames.len() > 1 {
+            if let Some(old) = self.frames.pop_back() {
+                let nanos = old.as_nanos();
+                self.sum -= nanos;
+                self.sum_sq -= nanos * nanos;
+                self.histogram.remove(old);
+            }
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.frames.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.frames.is_empty()
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &Duration> {
+        self.frames.iter()
+    }
+
+    pub const fn sum(&self) -> Duration {
+        Duration::from_nanos(self.sum as u64)
+    }
+
+    pub fn percentile(&self, q: f64) -> Duration {
+        self.histogram.percentile(q)
+    }
+
+    pub fn percentiles(&self) -> Percentiles {
+        Percentiles::new(&self.histogram)
+    }
+
+    pub fn std_dev(&self) -> Duration {
+        if self.frames.is_empty() {
+            return Duration::ZERO;
+        }
+
+        // n²·var = n·Σx² - (Σx)², 整数计算保证非负
+        let len = self.frames.len() as u128;
+        let variance = (len * self.sum_sq - self.sum * self.sum) as f64 / (len * len) as f64;
+
+        Duration::from_nanos(variance.sqrt() as u64)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // xorshift, 保证测试可复现
+    struct Rng(u64);
+
+    impl Rng {
+        fn next(&mut self) -> u64 {
+            self.0 ^= self.0 << 13;
+            self.0 ^= self.0 >> 7;
+            self.0 ^= self.0 << 17;
+            self.0
+        }
+
+        fn frame(&mut self) -> Duration {
+            // 1ms ~ 100ms
+            Duration::from_nanos(1_000_000 + self.next() % 99_000_000)
+        }
+    }
+
+    struct Naive(VecDeque<Duration>);
+
+    impl Naive {
+        fn push(&mut self, frame: Duration, window: Duration) {
+            self.0.push_front(frame);
+            while self.0.iter().sum::<Duration>() > window && self.0.len() > 1 {
+                self.0.pop_back();
+            }
+        }
+
+        fn mean(&self) -> f64 {
+            self.0.iter().map(|f| f.as_nanos() as f64).sum::<f64>() / self.0.len() as f64
+        }
+
+        fn std_dev(&self) -> f64 {
+            let mean = self.mean();
+            let variance = self
+                .0
+                .iter()
+                .map(|f| (f.as_nanos() as f64 - mean).powi(2))
+                .sum::<f64>()
+                / self.0.len() as f64;
+
+            variance.sqrt()
+        }
+    }
+
+    #[test]
+    fn matches_naive() {
+        for seed in 1..=20 {
+            let mut rng = Rng(seed);
+            let window = Duration::from_millis(50 + rng.next() % 3000);
+            let mut rolling = RollingWindow::new();
+            let mut naive = Naive(VecDeque::new());
+
+            for _ in 0..2000 {
+                let frame = rng.frame();
+                rolling.push(frame, window);
+                naive.push(frame, window);
+
+                assert!(rolling.iter().eq(naive.0.iter()), "seed {seed}");
+                assert_eq!(rolling.len(), naive.0.len());
+                assert_eq!(rolling.sum(), naive.0.iter().sum::<Duration>());
+                assert!(
+                    (rolling.std_dev().as_nanos() as f64 - naive.std_dev()).abs() <= 2.0,
+                    "seed {seed}"
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn keeps_one_frame() {
+        let mut rolling = RollingWindow::new();
+
+        rolling.push(Duration::from_millis(100), Duration::from_millis(10));
+        rolling.push(Duration::from_millis(200), Duration::from_millis(10));
+
+        assert_eq!(rolling.len(), 1);
+        assert_eq!(rolling.sum(), Duration::from_millis(200));
+        assert_eq!(rolling.std_dev(), Duration::ZERO);
+        assert_eq!(rolling.percentile(0.5), rolling.percentile(0.99));
+    }
+
+    #[test]
+    fn percentile_error_bound() {
+        let mut rng = Rng(42);
+        let mut rolling = RollingWindow::new();
+        let window = Duration::from_secs(3);
+
+        for _ in 0..5000 {
+            rolling.push(rng.frame(), window);
+
+            let mut sorted: Vec<_> = rolling.iter().copied().collect();
+            sorted.sort_unstable();
+
+            for q in [0.5, 0.9, 0.99, 0.999] {
+                let rank = ((sorted.len() as f64 * q).ceil() as usize).max(1);
+                let exact = sorted[rank - 1].as_secs_f64();
+                let approx = rolling.percentile(q).as_secs_f64();
+
+                assert!((approx - exact).abs() / exact <= 0.01, "q {q}");
+            }
+        }
+    }
+}
@@ -0,0 +1,181 @@
+/* Copyright 2023 shadow3aaa@gitbub.com
+*
+*  Licensed under the Apache License, Version 2.0 (the "License");
+*  you may not use this file except in compliance with the License.
+*  You may obtain a copy of the License at
+*
+*      http://www.apache.org/licenses/LICENSE-2.0
+*
+*  Unless required by applicable law or agreed to in writing, software
+*  distributed under the License is distributed on an "AS IS" BASIS,
+*  WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+*  See the License for the specific language governing permissions and
+*  limitations under the License. */
+use std::time::{Duration, Instant};
+
+use super::Buffer;
+use crate::framework::config::SceneConfig;
+
+// Loading: 帧率低于所有目标帧率但cpu负载很高
+// Idle: 帧率稳定且cpu负载很低, 如静态菜单
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Scene {
+    Normal,
+    Loading,
+    Idle,
+}
+
+impl ToString for Scene {
+    fn to_string(&self) -> String {
+        match self {
+            Self::Normal => "normal",
+            Self::Loading => "loading",
+            Self::Idle => "idle",
+        }
+        .into()
+    }
+}
+
+// 检测结果需要持续一段时间才切换场景
+#[derive(Debug)]
+pub struct SceneDetector {
+    scene: Scene,
+    candidate: Scene,
+    since: Instant,
+}
+
+impl SceneDetector {
+    pub fn new() -> Self {
+        Self {
+            scene: Scene::Normal,
+            candidate: Scene::Normal,
+            since: Instant::now(),
+        }
+    }
+
+    pub const fn scene(&self) -> Scene {
+        self.scene
+    }
+
+    fn update(&mut self, detected: Scene, hold: Duration) {
+        if detected != self.candidate {
+            self.candidate = detected;
+            self.since = Instant::now();
+        }
+
+        if self.candidate != self.scene && self.since.elapsed() >= hold {
+            self.scene = self.candidate;
+        }
+    }
+}
+
+impl Buffer {
+    pub fn update_scene(&mut self, usage: Option<f64>, config: &SceneConfig) -> Scene {
+        let detected = match usage {
+            _ if self.frametimes.is_empty() || self.rewarming() => Scene::Normal,
+            Some(usage) if self.target_fps.is_none() && usage >= config.loading_usage => {
+                Scene::Loading
+            }
+            Some(usage)
+                if self.target_fps.is_some()
+                    && self.deviation <= config.idle_deviation
+                    && usage <= config.idle_usage =>
+            {
+                Scene::Idle
+            }
+            _ => Scene::Normal,
+        };
+
+        self.scene
+            .update(detected, Duration::from_secs_f64(config.hold));
+        self.scene.scene()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::framework::config::TargetFps;
+
+    const HOLD: Duration = Duration::from_secs(2);
+
+    // 模拟经过elapsed后检测到detected
+    fn step(detector: &mut SceneDetector, detected: Scene, elapsed: Duration) -> Scene {
+        detector.since = detector.since.checked_sub(elapsed).unwrap();
+        detector.update(detected, HOLD);
+        detector.scene()
+    }
+
+    fn playing() -> Buffer {
+        let mut buffer = Buffer::new(TargetFps::Array(vec![30, 60]));
+        for _ in 0..10 {
+            buffer.push_frametime(Duration::from_millis(50));
+        }
+
+        buffer
+    }
+
+    #[test]
+    fn hold() {
+        let mut detector = SceneDetector::new();
+
+        assert_eq!(step(&mut detector, Scene::Loading, HOLD), Scene::Normal);
+        assert_eq!(step(&mut detector, Scene::Loading, HOLD / 2), Scene::Normal);
+        assert_eq!(
+            step(&mut detector, Scene::Loading, HOLD / 2),
+            Scene::Loading
+        );
+
+        // 检测结果变化时重新计时
+        assert_eq!(
+            step(&mut detector, Scene::Idle, Duration::ZERO),
+            Scene::Loading
+        );
+        assert_eq!(step(&mut detector, Scene::Normal, HOLD / 2), Scene::Loading);
+        assert_eq!(step(&mut detector, Scene::Idle, HOLD / 2), Scene::Loading);
+        assert_eq!(step(&mut detector, Scene::Idle, HOLD / 2), Scene::Loading);
+        assert_eq!(step(&mut detector, Scene::Idle, HOLD / 2), Scene::Idle);
+
+        // 检测结果回到当前场景时取消切换
+        assert_eq!(
+            step(&mut detector, Scene::Normal, Duration::ZERO),
+            Scene::Idle
+        );
+        assert_eq!(step(&mut detector, Scene::Idle, HOLD / 2), Scene::Idle);
+        assert_eq!(step(&mut detector, Scene::Idle, HOLD * 2), Scene::Idle);
+    }
+
+    #[test]
+    fn classify() {
+        let config = SceneConfig {
+            hold: 0.0,
+            ..SceneConfig::default()
+        };
+
+        // 没有目标帧率时看负载判断加载
+        let mut buffer = playing();
+        assert_eq!(buffer.update_scene(Some(0.7), &config), Scene::Loading);
+        assert_eq!(buffer.update_scene(Some(0.5), &config), Scene::Normal);
+        assert_eq!(buffer.update_scene(None, &config), Scene::Normal);
+        assert_eq!(buffer.update_scene(Some(0.1), &config), Scene::Normal);
+
+        // 有目标帧率, 帧时间稳定且负载低时为空闲
+        buffer.target_fps = Some(60);
+        buffer.deviation = 0.05;
+        assert_eq!(buffer.update_scene(Some(0.2), &config), Scene::Idle);
+        assert_eq!(buffer.update_scene(Some(0.7), &config), Scene::Normal);
+        assert_eq!(buffer.update_scene(None, &config), Scene::Normal);
+
+        buffer.deviation = 0.2;
+        assert_eq!(buffer.update_scene(Some(0.2), &config), Scene::Normal);
+
+        // 没有帧或者正在重新预热时不判断场景
+        let mut buffer = Buffer::new(TargetFps::Array(vec![30, 60]));
+        assert_eq!(buffer.update_scene(Some(0.9), &config), Scene::Normal);
+
+        let mut buffer = playing();
+        assert!(!buffer.push_frametime(Duration::from_secs(1)));
+        assert!(buffer.rewarming());
+        assert_eq!(buffer.update_scene(Some(0.9), &config), Scene::Normal);
+    }
+}
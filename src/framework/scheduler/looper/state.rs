@@ -0,0 +1,178 @@
+/* Copyright 2023 shadow3aaa@gitbub.com
+*
+*  Licensed under the Apache License, Version 2.0 (the "License");
+*  you may not use this file except in compliance with the License.
+*  You may obtain a copy of the License at
+*
+*      http://www.apache.org/licenses/LICENSE-2.0
+*
+*  Unless required by applicable law or agreed to in writing, software
+*  distributed under the License is distributed on an "AS IS" BASIS,
+*  WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+*  See the License for the specific language governing permissions and
+*  limitations under the License. */
+use std::time::{Duration, Instant};
+
+// Idle: 没有游戏
+// Warmup: 游戏已开始渲染, 等待预热结束
+// Active: fas工作中
+// Suspended: 游戏仍在前台但停止渲染, 处于被设置为暂停的窗口模式(如画中画)或者熄屏, 控制器已恢复默认
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum State {
+    Idle,
+    Warmup,
+    Active,
+    Suspended,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Event {
+    Frame,
+    WarmupElapsed,
+    FrameTimeout,
+    Hidden,
+    ScreenOff,
+    GameExit,
+}
+
+impl State {
+    pub const fn next(self, event: Event) -> Option<Self> {
+        match (self, event) {
+            (Self::Idle | Self::Suspended, Event::Frame) => Some(Self::Warmup),
+            (Self::Warmup, Event::WarmupElapsed) => Some(Self::Active),
+            (
+                Self::Warmup | Self::Active,
+                Event::FrameTimeout | Event::Hidden | Event::ScreenOff,
+            ) => Some(Self::Suspended),
+            (Self::Warmup | Self::Active | Self::Suspended, Event::GameExit) => Some(Self::Idle),
+            _ => None,
+        }
+    }
+}
+
+impl ToString for State {
+    fn to_string(&self) -> String {
+        match self {
+            Self::Idle => "idle",
+            Self::Warmup => "warmup",
+            Self::Active => "active",
+            Self::Suspended => "suspended",
+        }
+        .into()
+    }
+}
+
+pub struct StateMachine {
+    state: State,
+    timer: Instant,
+}
+
+impl StateMachine {
+    pub fn new() -> Self {
+        Self {
+            state: State::Idle,
+            timer: Instant::now(),
+        }
+    }
+
+    pub const fn state(&self) -> State {
+        self.state
+    }
+
+    // 处于当前状态的时间
+    pub fn elapsed(&self) -> Duration {
+        self.timer.elapsed()
+    }
+
+    // 返回发生的状态转移
+    pub fn handle(&mut self, event: Event) -> Option<(State, State)> {
+        let from = self.state;
+        let to = from.next(event)?;
+
+        self.state = to;
+        self.timer = Instant::now();
+
+        Some((from, to))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const EVENTS: [Event; 6] = [
+        Event::Frame,
+        Event::WarmupElapsed,
+        Event::FrameTimeout,
+        Event::Hidden,
+        Event::ScreenOff,
+        Event::GameExit,
+    ];
+
+    #[test]
+    fn transition_table() {
+        use State::{Active, Idle, Suspended, Warmup};
+
+        // 每行对应EVENTS中的事件顺序
+        let table = [
+            (Idle, [Some(Warmup), None, None, None, None, None]),
+            (
+                Warmup,
+                [
+                    None,
+                    Some(Active),
+                    Some(Suspended),
+                    Some(Suspended),
+                    Some(Suspended),
+                    Some(Idle),
+                ],
+            ),
+            (
+                Active,
+                [
+                    None,
+                    None,
+                    Some(Suspended),
+                    Some(Suspended),
+                    Some(Suspended),
+                    Some(Idle),
+                ],
+            ),
+            (
+                Suspended,
+                [Some(Warmup), None, None, None, None, Some(Idle)],
+            ),
+        ];
+
+        for (state, expected) in table {
+            for (event, to) in EVENTS.into_iter().zip(expected) {
+                assert_eq!(state.next(event), to, "{state:?} + {event:?}");
+            }
+        }
+    }
+
+    #[test]
+    fn machine_handle() {
+        let mut machine = StateMachine::new();
+
+        assert_eq!(machine.handle(Event::WarmupElapsed), None);
+        assert_eq!(machine.state(), State::Idle);
+
+        assert_eq!(
+            machine.handle(Event::Frame),
+            Some((State::Idle, State::Warmup))
+        );
+        assert_eq!(
+            machine.handle(Event::WarmupElapsed),
+            Some((State::Warmup, State::Active))
+        );
+        assert_eq!(
+            machine.handle(Event::ScreenOff),
+            Some((State::Active, State::Suspended))
+        );
+        assert_eq!(
+            machine.handle(Event::GameExit),
+            Some((State::Suspended, State::Idle))
+        );
+    }
+}
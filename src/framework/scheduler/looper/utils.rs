@@ -13,62 +13,161 @@
 *  limitations under the License. */
 use std::{
     collections::hash_map::Entry,
+    fmt::Write,
     time::{Duration, Instant},
 };
 
+#[cfg(debug_assertions)]
+use log::debug;
 use log::info;
 
-use super::{super::FasData, Buffer, Looper};
-use crate::framework::{config::TargetFps, error::Result, PerformanceController};
+use super::{
+    super::{producer, thermal::FpsStep, window::WindowMode, FasData},
+    Buffer, Event, Looper, Scene, State, StutterStats,
+};
+use crate::framework::{
+    config::{SceneAction, TargetFps, WindowAction},
+    error::Result,
+    PerformanceController,
+};
 
 impl<P: PerformanceController> Looper<P> {
     pub fn retain_topapp(&mut self) -> Result<()> {
         self.buffers
-            .retain(|(_, p), _| self.topapp_checker.is_topapp(*p));
+            .retain(|p, _| self.topapp_checker.is_topapp(p.pid));
 
         if self.buffers.is_empty() {
-            self.disable_fas()?;
-        } else {
-            self.enable_fas()?;
+            self.handle_event(Event::GameExit)?;
         }
 
         Ok(())
     }
 
-    pub fn disable_fas(&mut self) -> Result<()> {
-        if self.start {
-            self.controller.init_default(self.mode, &self.config)?;
-            self.start = false;
-            self.start_delayed = false;
+    // 丢弃长时间没有新帧, 或者所属进程已经退出/pid被复用的buffer
+    pub fn gc_buffers(&mut self) -> Result<()> {
+        self.gc_timer = Instant::now();
+
+        let gc_time = self.timing.buffer_gc();
+        self.buffers.retain(|p, buffer| {
+            let keep = buffer.last_update.elapsed() < gc_time
+                && producer::process_start_time(p.pid) == buffer.start_time;
+
+            #[cfg(debug_assertions)]
+            if !keep {
+                debug!("Drop stale buffer: {p:?}");
+            }
+
+            keep
+        });
+
+        if self.buffers.is_empty() {
+            self.handle_event(Event::GameExit)?;
         }
 
         Ok(())
     }
 
-    pub fn enable_fas(&mut self) -> Result<()> {
-        if !self.start {
-            self.delay_timer = Instant::now();
-            self.start = true;
+    pub fn publish_status(&mut self) {
+        self.status_timer = Instant::now();
+
+        for (id, value) in self.controller.status() {
+            let _ = self.node.update_node(id, value);
+        }
+
+        let _ = self
+            .node
+            .update_node("thermal".into(), self.thermal.status());
+        let _ = self
+            .node
+            .update_node("state".into(), self.state.state().to_string());
+        let _ = self
+            .node
+            .update_node("scene".into(), self.scene.to_string());
+        let _ = self
+            .node
+            .update_node("screen", if self.screen.is_on() { "on" } else { "off" });
+
+        let primary = self.primary_buffer();
+        let mut frame_stats = String::new();
+        for (producer, buffer) in &self.buffers {
+            let _ = writeln!(
+                frame_stats,
+                "pid: {} window: {} target_fps: {:?} primary: {} {} std_dev: {:.2}ms",
+                producer.pid,
+                self.window.mode(producer.pid).to_string(),
+                buffer.target_fps,
+                primary == Some(*producer),
+                buffer.frametimes.percentiles().to_string(),
+                buffer.frametimes.std_dev().as_secs_f64() * 1000.0
+            );
+        }
+        let _ = self.node.update_node("frame_stats".into(), frame_stats);
+        let _ = self
+            .node
+            .update_node("stutter".into(), self.stutter.to_string());
+    }
+
+    pub fn handle_event(&mut self, event: Event) -> Result<()> {
+        let Some((from, to)) = self.state.handle(event) else {
             return Ok(());
+        };
+
+        info!(
+            "Looper state: {} -> {} on {event:?}",
+            from.to_string(),
+            to.to_string()
+        );
+
+        if from == State::Active {
+            if let Some(ceiling) = self.thermal.reset() {
+                self.controller.set_ceiling(ceiling);
+            }
+            self.set_fps_cap(None);
+            self.scene = Scene::Normal;
+
+            self.controller.init_default(self.mode, &self.config)?;
+        }
+
+        if to == State::Idle {
+            if self.stutter.frames() > 0 {
+                info!(
+                    "Game session of [{}] ended: {}",
+                    self.game.as_deref().unwrap_or_default(),
+                    self.stutter.to_string()
+                );
+            }
+            self.stutter = StutterStats::default();
+
+            if let Some(mode) = self.game_mode.take() {
+                info!(
+                    "Leave game preferred mode {}, restore global mode {}",
+                    mode.to_string(),
+                    self.global_mode.to_string()
+                );
+                self.mode = self.global_mode;
+            }
+
+            self.game = None;
+            self.refresh_timing();
         }
 
-        // 延迟10秒启动fas
-        if !self.start_delayed && self.delay_timer.elapsed() > Duration::from_secs(10) {
+        if to == State::Active {
             self.controller.init_game(self.mode, &self.config)?;
-            self.start_delayed = true;
         }
 
+        self.publish_status();
+
         Ok(())
     }
 
-    pub fn buffer_update(&mut self, d: &FasData) {
-        if !self.topapp_checker.is_topapp(d.pid) || d.frametime.is_zero() {
-            return;
+    pub fn buffer_update(&mut self, d: &FasData) -> Result<()> {
+        let producer = d.producer;
+        if !self.topapp_checker.is_topapp(producer.pid) || d.frametime.is_zero() {
+            return Ok(());
         } else if d.target_fps == TargetFps::Value(0) {
             panic!("Target fps must be bigger than zero");
         }
 
-        let producer = (d.buffer, d.pid);
         let frametime = d.frametime;
         let target_fps = d.target_fps.clone();
 
@@ -78,18 +177,282 @@ impl<P: PerformanceController> Looper<P> {
             }
         }
 
+        // 新的buffer, 先清掉pid被新进程复用前留下的旧buffer
+        let start_time = if self.buffers.contains_key(&producer) {
+            None
+        } else {
+            let start_time = producer::process_start_time(producer.pid);
+            let len = self.buffers.len();
+            self.buffers
+                .retain(|p, buffer| p.pid != producer.pid || buffer.start_time == start_time);
+
+            // 游戏重启了, 先结束上一次的会话
+            if self.buffers.is_empty() && len > 0 {
+                self.handle_event(Event::GameExit)?;
+            }
+
+            start_time
+        };
+
+        // 游戏的第一个buffer, 切换到游戏偏好的模式
+        if self.buffers.is_empty() {
+            self.game = Some(d.pkg.clone());
+            self.game_mode = self.config.game_mode(&d.pkg);
+            self.refresh_timing();
+
+            if let Some(mode) = self.game_mode {
+                info!(
+                    "Game [{}] prefers mode {}, switch from global mode {}",
+                    d.pkg,
+                    mode.to_string(),
+                    self.global_mode.to_string()
+                );
+            }
+        }
+
+        let window_cap = self.window_cap(producer.pid);
         match self.buffers.entry(producer) {
             Entry::Occupied(mut o) => {
-                o.get_mut().push_frametime(frametime);
+                let buffer = o.get_mut();
+                if buffer.push_frametime(frametime) {
+                    self.stutter.record(frametime, buffer.last_jank);
+                }
             }
             Entry::Vacant(v) => {
-                info!("New fas buffer on game: [{}] pid: [{}]", d.pkg, d.pid);
+                info!(
+                    "New fas buffer on game: [{}] pid: [{}] session: [{}]",
+                    d.pkg, producer.pid, producer.session
+                );
 
                 let mut buffer = Buffer::new(target_fps);
-                buffer.push_frametime(frametime);
+                buffer.start_time = start_time;
+                buffer.window_cap = window_cap;
+                buffer.fps_cap = self.fps_cap;
+                buffer.timing = self.timing;
+                buffer.display = self.display.info().clone();
+                if buffer.push_frametime(frametime) {
+                    self.stutter.record(frametime, buffer.last_jank);
+                }
                 v.insert(buffer);
             }
         }
+
+        Ok(())
+    }
+
+    pub fn update_scene(&mut self) -> Result<()> {
+        self.scene_timer = Instant::now();
+
+        let usage = self.controller.usage();
+        let config = self.config.scene_config();
+        let scenes: Vec<_> = self
+            .buffers
+            .values_mut()
+            .map(|buffer| buffer.update_scene(usage, &config))
+            .collect();
+
+        // 任一buffer加载中即为加载, 全部空闲才为空闲
+        let scene = if scenes.contains(&Scene::Loading) {
+            Scene::Loading
+        } else if !scenes.is_empty() && scenes.iter().all(|s| *s == Scene::Idle) {
+            Scene::Idle
+        } else {
+            Scene::Normal
+        };
+
+        if scene != self.scene {
+            info!("Scene: {} -> {}", self.scene.to_string(), scene.to_string());
+            self.scene = scene;
+            self.publish_status();
+        }
+
+        let mode_config = self.config.mode_config(self.mode);
+        let action = match scene {
+            Scene::Loading => mode_config.loading_action,
+            Scene::Idle => mode_config.idle_action,
+            Scene::Normal => SceneAction::None,
+        };
+
+        // 和帧策略共用控制间隔, 避免互相覆盖. 加载中可能还没有目标帧率, 按1fps计算
+        let target_fps = self.control_target_fps().unwrap_or(1);
+        let control_interval = Duration::from_secs(self.timing.control_interval.into());
+
+        match action {
+            SceneAction::Boost => {
+                if self.last_control.elapsed() * target_fps > control_interval {
+                    self.last_control = Instant::now();
+                    self.limit_delay = Duration::from_secs(self.timing.big_jank_limit_delay.into());
+                    self.controller.release_max(self.mode, &self.config)?;
+                }
+            }
+            SceneAction::Limit => {
+                if self.last_control.elapsed() * target_fps > self.limit_delay {
+                    self.last_control = Instant::now();
+                    self.limit_delay = Duration::from_secs(self.timing.limit_delay.into());
+                    self.controller.limit(self.mode, &self.config)?;
+                }
+            }
+            SceneAction::None => (),
+        }
+
+        Ok(())
+    }
+
+    pub fn apply_fps_step(&mut self, step: FpsStep) {
+        let mut target_fpses: Vec<_> = self
+            .buffers
+            .values()
+            .flat_map(Buffer::target_fpses)
+            .collect();
+        target_fpses.sort_unstable();
+        target_fpses.dedup();
+
+        let cap = match (step, self.fps_cap) {
+            (FpsStep::Down, cap) => {
+                let Some(current) =
+                    cap.or_else(|| self.buffers.values().filter_map(|b| b.target_fps).max())
+                else {
+                    return;
+                };
+
+                let Some(lower) = target_fpses.iter().copied().filter(|t| *t < current).max()
+                else {
+                    return;
+                };
+
+                info!("Skin temperature over setpoint, cap target fps to {lower}");
+                Some(lower)
+            }
+            (FpsStep::Up, Some(cap)) => {
+                let higher = target_fpses.iter().copied().find(|t| *t > cap);
+
+                if higher == target_fpses.last().copied() {
+                    info!("Skin temperature back under setpoint, target fps uncapped");
+                    None
+                } else {
+                    info!("Skin temperature cooling down, cap target fps to {higher:?}");
+                    higher
+                }
+            }
+            (FpsStep::Up, None) => return,
+        };
+
+        self.set_fps_cap(cap);
+    }
+
+    pub fn update_display(&mut self) {
+        let Some(info) = self.display.update() else {
+            return;
+        };
+
+        info!(
+            "Display refresh rate: {:?} supported: {:?}",
+            info.refresh_rate, info.modes
+        );
+
+        for buffer in self.buffers.values_mut() {
+            buffer.display = info.clone();
+        }
+    }
+
+    pub fn update_screen(&mut self) -> Result<()> {
+        let Some(on) = self.screen.update() else {
+            return Ok(());
+        };
+
+        info!("Screen turned {}", if on { "on" } else { "off" });
+
+        // 亮屏后由下一帧重新进入预热
+        if on {
+            self.publish_status();
+        } else {
+            self.handle_event(Event::ScreenOff)?;
+        }
+
+        Ok(())
+    }
+
+    pub fn update_window(&mut self) -> Result<()> {
+        let top_changed = self.topapp_checker.take_changed();
+        if !self.window.update(top_changed) {
+            return Ok(());
+        }
+
+        let caps: Vec<_> = self
+            .buffers
+            .keys()
+            .map(|p| (*p, self.window_cap(p.pid)))
+            .collect();
+        for (producer, cap) in caps {
+            let Some(buffer) = self.buffers.get_mut(&producer) else {
+                continue;
+            };
+
+            if buffer.window_cap != cap {
+                info!(
+                    "Game pid: [{}] window: {}, target fps cap: {cap:?}",
+                    producer.pid,
+                    self.window.mode(producer.pid).to_string()
+                );
+                buffer.window_cap = cap;
+            }
+        }
+
+        if self.window_suspended() {
+            self.handle_event(Event::Hidden)?;
+        }
+
+        Ok(())
+    }
+
+    fn window_action(&self, pid: i32) -> WindowAction {
+        let config = self.config.window_mode_config();
+
+        match self.window.mode(pid) {
+            WindowMode::Fullscreen => WindowAction::Keep,
+            WindowMode::Split => config.split,
+            WindowMode::Freeform => config.freeform,
+            WindowMode::Pip => config.pip,
+        }
+    }
+
+    fn window_cap(&self, pid: i32) -> Option<u32> {
+        (self.window_action(pid) == WindowAction::Cap)
+            .then(|| self.config.window_mode_config().fps_cap)
+    }
+
+    // 所有buffer所在的窗口都被设置为暂停
+    pub fn window_suspended(&self) -> bool {
+        !self.buffers.is_empty()
+            && self
+                .buffers
+                .keys()
+                .all(|p| self.window_action(p.pid) == WindowAction::Suspend)
+    }
+
+    pub fn refresh_timing(&mut self) {
+        self.timing_timer = Instant::now();
+
+        let timing = self.config.timing_config(self.mode, self.game.as_deref());
+        if timing == self.timing {
+            return;
+        }
+
+        #[cfg(debug_assertions)]
+        debug!("Timing config changed: {timing:?}");
+
+        self.timing = timing;
+        for buffer in self.buffers.values_mut() {
+            buffer.timing = timing;
+        }
+    }
+
+    fn set_fps_cap(&mut self, cap: Option<u32>) {
+        self.fps_cap = cap;
+
+        for buffer in self.buffers.values_mut() {
+            buffer.fps_cap = cap;
+        }
     }
 
     pub fn latest_update_elapsed(&self) -> Duration {
@@ -100,3 +463,126 @@ impl<P: PerformanceController> Looper<P> {
             .unwrap_or_default()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use super::{
+        super::tests::{looper, TOP_PID},
+        *,
+    };
+    use crate::{
+        framework::{config::Config, node::Mode, scheduler::producer::Producer},
+        misc::Fixture,
+    };
+
+    #[test]
+    fn apply_fps_step() {
+        let fixture = Fixture::new("looper-fps-step");
+        let mut looper = looper(&fixture);
+
+        let mut buffer = Buffer::new(TargetFps::Array(vec![30, 60, 90, 120]));
+        buffer.target_fps = Some(120);
+        looper.buffers.insert(Producer::new(1, 1, 1), buffer);
+
+        // 没有上限时不会升档
+        looper.apply_fps_step(FpsStep::Up);
+        assert_eq!(looper.fps_cap, None);
+
+        // 从当前目标帧率开始逐级降档
+        looper.apply_fps_step(FpsStep::Down);
+        assert_eq!(looper.fps_cap, Some(90));
+        looper.apply_fps_step(FpsStep::Down);
+        looper.apply_fps_step(FpsStep::Down);
+        assert_eq!(looper.fps_cap, Some(30));
+        looper.apply_fps_step(FpsStep::Down);
+        assert_eq!(looper.fps_cap, Some(30));
+        assert!(looper.buffers.values().all(|b| b.fps_cap == Some(30)));
+
+        // 逐级恢复, 恢复到最高档时取消上限
+        looper.apply_fps_step(FpsStep::Up);
+        assert_eq!(looper.fps_cap, Some(60));
+        looper.apply_fps_step(FpsStep::Up);
+        assert_eq!(looper.fps_cap, Some(90));
+        looper.apply_fps_step(FpsStep::Up);
+        assert_eq!(looper.fps_cap, None);
+        assert!(looper.buffers.values().all(|b| b.fps_cap.is_none()));
+    }
+
+    #[test]
+    fn scene_action_gated() {
+        let fixture = Fixture::new("looper-scene");
+        let mut looper = looper(&fixture);
+
+        // 场景检测不需要持续
+        let games = fs::read_to_string("module/games.toml").unwrap();
+        let path = fixture.write("games.toml", games.replace("hold = 2.0", "hold = 0.0"));
+        looper.config = Config::new(path, "module/games.toml".into()).unwrap();
+
+        let mut buffer = Buffer::new(TargetFps::Array(vec![30, 60]));
+        for _ in 0..10 {
+            buffer.push_frametime(Duration::from_millis(50));
+        }
+        looper.buffers.insert(Producer::new(1, 1, TOP_PID), buffer);
+
+        // 加载中, 刚刚控制过时不拉满
+        looper.mode = Mode::Performance;
+        looper.controller.usage.set(Some(0.9));
+        looper.last_control = Instant::now();
+        looper.update_scene().unwrap();
+        assert_eq!(looper.scene, Scene::Loading);
+        assert!(looper.controller.take().is_empty());
+
+        looper.last_control = Instant::now().checked_sub(Duration::from_secs(2)).unwrap();
+        looper.update_scene().unwrap();
+        assert_eq!(looper.controller.take(), ["release_max"]);
+        assert_eq!(looper.limit_delay, Duration::from_secs(5));
+
+        // 空闲, 需要等待拉满后的limit_delay
+        looper.mode = Mode::Balance;
+        looper.controller.usage.set(Some(0.1));
+        for buffer in looper.buffers.values_mut() {
+            buffer.target_fps = Some(60);
+            buffer.deviation = 0.0;
+        }
+        looper.update_scene().unwrap();
+        assert_eq!(looper.scene, Scene::Idle);
+        assert!(looper.controller.take().is_empty());
+
+        looper.last_control = Instant::now().checked_sub(Duration::from_secs(1)).unwrap();
+        looper.update_scene().unwrap();
+        assert_eq!(looper.controller.take(), ["limit"]);
+        assert_eq!(looper.limit_delay, Duration::from_secs(1));
+    }
+
+    #[test]
+    fn gc_reused_pid() {
+        let fixture = Fixture::new("looper-gc");
+        let mut looper = looper(&fixture);
+
+        let pid = i32::try_from(std::process::id()).unwrap();
+        let start_time = producer::process_start_time(pid);
+        assert!(start_time.is_some());
+
+        let mut live = Buffer::new(TargetFps::Value(60));
+        live.start_time = start_time;
+        looper.buffers.insert(Producer::new(1, 1, pid), live);
+
+        // 同一pid, 启动时间不同, 说明pid已经被新进程复用
+        let mut stale = Buffer::new(TargetFps::Value(60));
+        stale.start_time = start_time.map(|t| t.saturating_sub(1));
+        looper.buffers.insert(Producer::new(2, 2, pid), stale);
+
+        let mut expired = Buffer::new(TargetFps::Value(60));
+        expired.start_time = start_time;
+        expired.last_update = Instant::now()
+            .checked_sub(looper.timing.buffer_gc())
+            .unwrap();
+        looper.buffers.insert(Producer::new(3, 3, pid), expired);
+
+        looper.gc_buffers().unwrap();
+        let kept: Vec<_> = looper.buffers.keys().copied().collect();
+        assert_eq!(kept, [Producer::new(1, 1, pid)]);
+    }
+}
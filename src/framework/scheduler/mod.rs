@@ -11,11 +11,19 @@
 *  WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
 *  See the License for the specific language governing permissions and
 *  limitations under the License. */
+mod auto_mode;
 mod binder;
+mod display;
 mod looper;
+mod producer;
+mod screen;
+mod thermal;
 mod topapp;
+mod window;
 
-use std::time::Duration;
+use std::{sync::mpsc, time::Duration};
+
+use parking_lot::Mutex;
 
 use super::{
     config::{Config, TargetFps},
@@ -26,19 +34,22 @@ use super::{
 
 use self::binder::FasServer;
 use looper::Looper;
+use producer::Producer;
 
 #[derive(Debug, Clone)]
 pub enum BinderMessage {
     Data(FasData),
-    RemoveBuffer((i64, i32)),
+    RemoveBuffer(Producer),
+    // 空闲时唤醒Looper
+    ModeChanged,
+    ConfigReloaded,
 }
 
 #[derive(Debug, Clone)]
 pub struct FasData {
-    pub buffer: i64,
+    pub producer: Producer,
     pub target_fps: TargetFps,
     pub pkg: String,
-    pub pid: i32,
     pub frametime: Duration,
     pub cpu: i32,
 }
@@ -79,7 +90,22 @@ impl<P: PerformanceController> Scheduler<P> {
             .controller
             .ok_or(Error::SchedulerMissing("Controller"))?;
 
-        let rx = FasServer::run_server(config.clone())?;
+        let (sx, rx) = mpsc::channel();
+        FasServer::run_server(config.clone(), sx.clone())?;
+
+        {
+            let sx = Mutex::new(sx.clone());
+            Node::watch_mode(move || {
+                let _ = sx.lock().send(BinderMessage::ModeChanged);
+            })?;
+        }
+
+        {
+            let sx = Mutex::new(sx);
+            config.on_reload(move || {
+                let _ = sx.lock().send(BinderMessage::ConfigReloaded);
+            });
+        }
 
         Looper::new(rx, config, node, controller).enter_loop()
     }
@@ -0,0 +1,67 @@
+/* Copyright 2023 shadow3aaa@gitbub.com
+*
+*  Licensed under the Apache License, Version 2.0 (the "License");
+*  you may not use this file except in compliance with the License.
+*  You may obtain a copy of the License at
+*
+*      http://www.apache.org/licenses/LICENSE-2.0
+*
+*  Unless required by applicable law or agreed to in writing, software
+*  distributed under the License is distributed on an "AS IS" BASIS,
+*  WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+*  See the License for the specific language governing permissions and
+*  limitations under the License. */
+use std::fs;
+
+// 客户端为每个surface分配session, 指针或pid被复用时不会和旧的buffer混淆
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Producer {
+    pub buffer: i64,
+    pub session: i64,
+    pub pid: i32,
+}
+
+impl Producer {
+    pub const fn new(buffer: i64, session: i64, pid: i32) -> Self {
+        Self {
+            buffer,
+            session,
+            pid,
+        }
+    }
+}
+
+// /proc/<pid>/stat第22项, 进程启动时间(clock ticks)
+pub fn process_start_time(pid: i32) -> Option<u64> {
+    let stat = fs::read_to_string(format!("/proc/{pid}/stat")).ok()?;
+    parse_start_time(&stat)
+}
+
+fn parse_start_time(stat: &str) -> Option<u64> {
+    // comm中可能有空格和括号, 从最后一个')'之后开始解析
+    let (_, rest) = stat.rsplit_once(')')?;
+    rest.split_whitespace().nth(19)?.parse().ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_stat() {
+        let stat = "12345 (Unity Main) (x) R 1 12345 0 0 -1 4194560 100 0 0 0 50 10 0 0 20 0 \
+                    30 0 987654 1000 200 18446744073709551615";
+        assert_eq!(parse_start_time(stat), Some(987_654));
+
+        assert_eq!(parse_start_time("12345 (game) R 1 2 3"), None);
+        assert_eq!(parse_start_time("12345 game R"), None);
+    }
+
+    #[test]
+    fn own_start_time() {
+        let pid = i32::try_from(std::process::id()).unwrap();
+        assert!(process_start_time(pid).is_some());
+        assert_eq!(process_start_time(pid), process_start_time(pid));
+        assert_eq!(process_start_time(-1), None);
+    }
+}
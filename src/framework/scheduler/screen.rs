@@ -0,0 +1,276 @@
+/* Copyright 2023 shadow3aaa@gitbub.com
+*
+*  Licensed under the Apache License, Version 2.0 (the "License");
+*  you may not use this file except in compliance with the License.
+*  You may obtain a copy of the License at
+*
+*      http://www.apache.org/licenses/LICENSE-2.0
+*
+*  Unless required by applicable law or agreed to in writing, software
+*  distributed under the License is distributed on an "AS IS" BASIS,
+*  WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+*  See the License for the specific language governing permissions and
+*  limitations under the License. */
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    process::Command,
+    time::{Duration, Instant},
+};
+
+use log::info;
+
+const REFRESH_TIME: Duration = Duration::from_secs(1);
+
+// 屏幕状态来源, 读取失败时返回None, 交给下一个来源
+pub trait ScreenSource {
+    fn name(&self) -> &'static str;
+    fn screen_on(&self) -> Option<bool>;
+}
+
+// /sys/class/drm/*/dpms, 任一连接器为On即亮屏
+struct Dpms(Vec<PathBuf>);
+
+impl ScreenSource for Dpms {
+    fn name(&self) -> &'static str {
+        "drm dpms"
+    }
+
+    fn screen_on(&self) -> Option<bool> {
+        let states: Vec<_> = self
+            .0
+            .iter()
+            .filter_map(|p| fs::read_to_string(p).ok())
+            .collect();
+
+        if states.is_empty() {
+            return None;
+        }
+
+        Some(states.iter().any(|s| s.trim() == "On"))
+    }
+}
+
+// 背光亮度为0即熄屏
+struct Backlight(Vec<PathBuf>);
+
+impl ScreenSource for Backlight {
+    fn name(&self) -> &'static str {
+        "backlight"
+    }
+
+    fn screen_on(&self) -> Option<bool> {
+        let levels: Vec<u32> = self
+            .0
+            .iter()
+            .filter_map(|p| fs::read_to_string(p).ok()?.trim().parse().ok())
+            .collect();
+
+        if levels.is_empty() {
+            return None;
+        }
+
+        Some(levels.iter().any(|l| *l > 0))
+    }
+}
+
+// PowerManagerService的mWakefulness, Dozing / Asleep即熄屏
+struct Wakefulness;
+
+impl ScreenSource for Wakefulness {
+    fn name(&self) -> &'static str {
+        "dumpsys power"
+    }
+
+    fn screen_on(&self) -> Option<bool> {
+        let dump = Command::new("dumpsys").arg("power").output().ok()?;
+        let dump = String::from_utf8_lossy(&dump.stdout);
+
+        let (_, state) = dump.split_once("mWakefulness=")?;
+        let state = state.split_whitespace().next()?;
+
+        Some(state == "Awake")
+    }
+}
+
+pub struct ScreenMonitor {
+    sources: Vec<Box<dyn ScreenSource>>,
+    on: bool,
+    last_refresh: Instant,
+}
+
+impl ScreenMonitor {
+    pub fn new() -> Self {
+        Self::from_root("/")
+    }
+
+    fn from_root<P: AsRef<Path>>(root: P) -> Self {
+        let root = root.as_ref();
+        let mut sources: Vec<Box<dyn ScreenSource>> = Vec::new();
+
+        let dpms = Self::glob(&root.join("sys/class/drm"), "dpms");
+        if !dpms.is_empty() {
+            sources.push(Box::new(Dpms(dpms)));
+        }
+
+        let mut backlight = Self::glob(&root.join("sys/class/backlight"), "brightness");
+        backlight.push(root.join("sys/class/leds/lcd-backlight/brightness"));
+        sources.push(Box::new(Backlight(backlight)));
+
+        sources.push(Box::new(Wakefulness));
+
+        Self::with_sources(sources)
+    }
+
+    // 按顺序使用第一个能读到状态的来源
+    pub fn with_sources(sources: Vec<Box<dyn ScreenSource>>) -> Self {
+        let mut monitor = Self {
+            sources,
+            on: true,
+            last_refresh: Instant::now(),
+        };
+
+        if let Some((name, on)) = monitor.read() {
+            info!("Screen state source: {name}");
+            monitor.on = on;
+        }
+
+        monitor
+    }
+
+    pub const fn is_on(&self) -> bool {
+        self.on
+    }
+
+    // 返回变化后的屏幕状态
+    pub fn update(&mut self) -> Option<bool> {
+        if self.last_refresh.elapsed() < REFRESH_TIME {
+            return None;
+        }
+        self.last_refresh = Instant::now();
+
+        let (_, on) = self.read()?;
+        if on == self.on {
+            return None;
+        }
+
+        self.on = on;
+        Some(on)
+    }
+
+    fn read(&self) -> Option<(&'static str, bool)> {
+        self.sources
+            .iter()
+            .find_map(|s| s.screen_on().map(|on| (s.name(), on)))
+    }
+
+    fn glob(dir: &Path, file: &str) -> Vec<PathBuf> {
+        fs::read_dir(dir)
+            .into_iter()
+            .flatten()
+            .filter_map(|e| Some(e.ok()?.path().join(file)))
+            .filter(|p| p.exists())
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{cell::Cell, rc::Rc};
+
+    use super::*;
+    use crate::misc::Fixture;
+
+    // 状态由测试控制的来源, None表示读取失败
+    struct Fake(&'static str, Rc<Cell<Option<bool>>>);
+
+    impl ScreenSource for Fake {
+        fn name(&self) -> &'static str {
+            self.0
+        }
+
+        fn screen_on(&self) -> Option<bool> {
+            self.1.get()
+        }
+    }
+
+    fn fake(
+        name: &'static str,
+        on: Option<bool>,
+    ) -> (Box<dyn ScreenSource>, Rc<Cell<Option<bool>>>) {
+        let state = Rc::new(Cell::new(on));
+        (Box::new(Fake(name, state.clone())), state)
+    }
+
+    fn update(monitor: &mut ScreenMonitor) -> Option<bool> {
+        monitor.last_refresh = monitor.last_refresh.checked_sub(REFRESH_TIME).unwrap();
+        monitor.update()
+    }
+
+    #[test]
+    fn source_fallback_order() {
+        let (first, first_state) = fake("first", None);
+        let (second, _) = fake("second", Some(false));
+        let (third, _) = fake("third", Some(true));
+        let monitor = ScreenMonitor::with_sources(vec![first, second, third]);
+
+        assert!(!monitor.is_on());
+        assert_eq!(monitor.read(), Some(("second", false)));
+
+        first_state.set(Some(true));
+        assert_eq!(monitor.read(), Some(("first", true)));
+
+        // 所有来源都读取失败时保持原状态
+        let mut monitor = ScreenMonitor::with_sources(vec![fake("none", None).0]);
+        assert!(monitor.is_on());
+        assert_eq!(update(&mut monitor), None);
+        assert!(monitor.is_on());
+    }
+
+    #[test]
+    fn transitions() {
+        let (source, state) = fake("fake", Some(true));
+        let mut monitor = ScreenMonitor::with_sources(vec![source]);
+        assert!(monitor.is_on());
+        assert_eq!(update(&mut monitor), None);
+
+        state.set(Some(false));
+        // 刷新间隔内不重新读取
+        assert_eq!(monitor.update(), None);
+        assert_eq!(update(&mut monitor), Some(false));
+        assert_eq!(update(&mut monitor), None);
+        assert!(!monitor.is_on());
+
+        state.set(Some(true));
+        assert_eq!(update(&mut monitor), Some(true));
+        assert_eq!(update(&mut monitor), None);
+        assert!(monitor.is_on());
+    }
+
+    #[test]
+    fn sysfs_sources() {
+        let fixture = Fixture::new("screen-sysfs");
+        fixture.write("sys/class/drm/card0-DSI-1/dpms", "Off\n");
+        fixture.write("sys/class/drm/card0-DP-1/dpms", "Off\n");
+        fixture.write("sys/class/backlight/panel0-backlight/brightness", "0\n");
+        fixture.write("sys/class/leds/lcd-backlight/brightness", "255\n");
+
+        let monitor = ScreenMonitor::from_root(fixture.path());
+        assert_eq!(monitor.read(), Some(("drm dpms", false)));
+
+        // 任一连接器为On即亮屏
+        fixture.write("sys/class/drm/card0-DP-1/dpms", "On\n");
+        assert_eq!(monitor.read(), Some(("drm dpms", true)));
+
+        // dpms读取失败时使用背光, 任一背光不为0即亮屏
+        fs::remove_dir_all(fixture.path().join("sys/class/drm")).unwrap();
+        assert_eq!(monitor.read(), Some(("backlight", true)));
+
+        fixture.write("sys/class/leds/lcd-backlight/brightness", "0\n");
+        assert_eq!(monitor.read(), Some(("backlight", false)));
+
+        // 无法解析的亮度被忽略
+        fixture.write("sys/class/backlight/panel0-backlight/brightness", "bad\n");
+        assert_eq!(monitor.read(), Some(("backlight", false)));
+    }
+}
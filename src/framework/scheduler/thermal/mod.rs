@@ -0,0 +1,178 @@
+/* Copyright 2023 shadow3aaa@gitbub.com
+*
+*  Licensed under the Apache License, Version 2.0 (the "License");
+*  you may not use this file except in compliance with the License.
+*  You may obtain a copy of the License at
+*
+*      http://www.apache.org/licenses/LICENSE-2.0
+*
+*  Unless required by applicable law or agreed to in writing, software
+*  distributed under the License is distributed on an "AS IS" BASIS,
+*  WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+*  See the License for the specific language governing permissions and
+*  limitations under the License. */
+mod sensor;
+mod skin;
+
+use std::time::{Duration, Instant};
+
+use log::info;
+
+use crate::framework::{config::Config, node::Mode};
+use sensor::Sensor;
+use skin::SkinGovernor;
+
+pub use skin::FpsStep;
+
+const REFRESH_TIME: Duration = Duration::from_secs(1);
+const CPU_SENSORS: [&str; 4] = ["cpu", "soc", "tsens", "mtktscpu"];
+const SKIN_SENSORS: [&str; 4] = ["skin", "quiet", "shell", "xo_therm"];
+const MIN_CEILING: f64 = 0.3;
+
+pub struct ThermalMonitor {
+    cpu: Sensor,
+    skin: Sensor,
+    skin_governor: SkinGovernor,
+    timer: Instant,
+    pub temp: Option<f64>,
+    pub skin_temp: Option<f64>,
+    pub ceiling: f64,
+    fps_step: Option<FpsStep>,
+    throttle_start: Option<Instant>,
+    throttle_count: usize,
+    throttle_total: Duration,
+}
+
+impl ThermalMonitor {
+    pub fn new() -> Self {
+        Self {
+            cpu: Sensor::new("Cpu", &CPU_SENSORS),
+            skin: Sensor::new("Skin", &SKIN_SENSORS),
+            skin_governor: SkinGovernor::new(),
+            timer: Instant::now(),
+            temp: None,
+            skin_temp: None,
+            ceiling: 1.0,
+            fps_step: None,
+            throttle_start: None,
+            throttle_count: 0,
+            throttle_total: Duration::ZERO,
+        }
+    }
+
+    pub const fn throttled(&self) -> bool {
+        self.throttle_start.is_some()
+    }
+
+    // 返回变化后的温控上限
+    pub fn update(&mut self, config: &Config, mode: Mode) -> Option<f64> {
+        if self.timer.elapsed() < REFRESH_TIME {
+            return None;
+        }
+        self.timer = Instant::now();
+
+        let thermal_config = config.thermal_config();
+        let mode_config = config.mode_config(mode);
+
+        self.temp = self.cpu.read(thermal_config.sensors);
+        self.skin_temp = self.skin.read(thermal_config.skin_sensors);
+
+        if let Some(step) = self
+            .skin_governor
+            .update(self.skin_temp, mode_config.skin_setpoint)
+        {
+            self.fps_step = Some(step);
+        }
+
+        let ceiling = match (
+            self.temp,
+            mode_config.thermal_threshold,
+            mode_config.thermal_limit,
+        ) {
+            (Some(temp), Some(threshold), Some(limit)) => Self::ceiling(
+                temp,
+                threshold,
+                limit,
+                mode_config.thermal_min_ceiling.unwrap_or(MIN_CEILING),
+            ),
+            _ => 1.0,
+        };
+
+        self.set_ceiling(ceiling)
+    }
+
+    // threshold到limit之间从1.0线性降到floor, 不会一直压在最低频率
+    fn ceiling(temp: f64, threshold: f64, limit: f64, floor: f64) -> f64 {
+        if limit <= threshold {
+            return 1.0;
+        }
+
+        let ratio = ((temp - threshold) / (limit - threshold)).clamp(0.0, 1.0);
+        (1.0 - ratio * (1.0 - floor)).clamp(floor, 1.0)
+    }
+
+    pub const fn take_fps_step(&mut self) -> Option<FpsStep> {
+        self.fps_step.take()
+    }
+
+    pub fn reset(&mut self) -> Option<f64> {
+        self.skin_governor.reset();
+        self.fps_step = None;
+        self.set_ceiling(1.0)
+    }
+
+    fn set_ceiling(&mut self, ceiling: f64) -> Option<f64> {
+        if (ceiling - self.ceiling).abs() < 0.01 {
+            return None;
+        }
+
+        if ceiling < 1.0 && self.throttle_start.is_none() {
+            info!("Thermal throttling started, temp: {:?}", self.temp);
+            self.throttle_start = Some(Instant::now());
+            self.throttle_count += 1;
+        } else if ceiling >= 1.0 {
+            if let Some(start) = self.throttle_start.take() {
+                let elapsed = start.elapsed();
+                info!("Thermal throttling ended after {elapsed:?}");
+                self.throttle_total += elapsed;
+            }
+        }
+
+        self.ceiling = ceiling;
+        Some(ceiling)
+    }
+
+    pub fn status(&self) -> String {
+        let throttle_total =
+            self.throttle_total + self.throttle_start.map(|s| s.elapsed()).unwrap_or_default();
+        let format_temp =
+            |t: Option<f64>| t.map_or_else(|| "unknown".into(), |t| format!("{t:.1}"));
+
+        format!(
+            "temp: {} skin_temp: {} ceiling: {:.2} throttled: {} throttle_count: {} throttle_total: {}s\n",
+            format_temp(self.temp),
+            format_temp(self.skin_temp),
+            self.ceiling,
+            self.throttled(),
+            self.throttle_count,
+            throttle_total.as_secs(),
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    #[allow(clippy::float_cmp)]
+    fn ceiling_floor() {
+        assert_eq!(ThermalMonitor::ceiling(60.0, 70.0, 85.0, 0.3), 1.0);
+        assert_eq!(ThermalMonitor::ceiling(70.0, 70.0, 85.0, 0.3), 1.0);
+        assert!((ThermalMonitor::ceiling(77.5, 70.0, 85.0, 0.3) - 0.65).abs() < 1e-9);
+        assert!((ThermalMonitor::ceiling(85.0, 70.0, 85.0, 0.3) - 0.3).abs() < 1e-9);
+        assert!((ThermalMonitor::ceiling(120.0, 70.0, 85.0, 0.3) - 0.3).abs() < 1e-9);
+        assert_eq!(ThermalMonitor::ceiling(120.0, 70.0, 85.0, 0.0), 0.0);
+        assert_eq!(ThermalMonitor::ceiling(120.0, 85.0, 70.0, 0.3), 1.0);
+    }
+}
@@ -0,0 +1,77 @@
+/* Copyright 2023 shadow3aaa@gitbub.com
+*
+*  Licensed under the Apache License, Version 2.0 (the "License");
+*  you may not use this file except in compliance with the License.
+*  You may obtain a copy of the License at
+*
+*      http://www.apache.org/licenses/LICENSE-2.0
+*
+*  Unless required by applicable law or agreed to in writing, software
+*  distributed under the License is distributed on an "AS IS" BASIS,
+*  WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+*  See the License for the specific language governing permissions and
+*  limitations under the License. */
+use std::{fs, path::PathBuf};
+
+use log::info;
+
+const THERMAL_PATH: &str = "/sys/class/thermal";
+
+// 按类型名选择一组thermal_zone, 取其中的最高温度
+pub struct Sensor {
+    name: &'static str,
+    defaults: &'static [&'static str],
+    patterns: Vec<String>,
+    zones: Vec<PathBuf>,
+}
+
+impl Sensor {
+    pub const fn new(name: &'static str, defaults: &'static [&'static str]) -> Self {
+        Self {
+            name,
+            defaults,
+            patterns: Vec::new(),
+            zones: Vec::new(),
+        }
+    }
+
+    pub fn read(&mut self, patterns: Vec<String>) -> Option<f64> {
+        if patterns != self.patterns || self.zones.is_empty() {
+            self.zones = self.find_zones(&patterns);
+            self.patterns = patterns;
+        }
+
+        // 温度单位通常为毫摄氏度, 部分内核直接给出摄氏度
+        self.zones
+            .iter()
+            .filter_map(|z| fs::read_to_string(z).ok()?.trim().parse::<f64>().ok())
+            .map(|t| if t.abs() > 1000.0 { t / 1000.0 } else { t })
+            .reduce(f64::max)
+    }
+
+    fn find_zones(&self, patterns: &[String]) -> Vec<PathBuf> {
+        let Ok(dir) = fs::read_dir(THERMAL_PATH) else {
+            return Vec::new();
+        };
+
+        let zones: Vec<_> = dir
+            .filter_map(|d| Some(d.ok()?.path()))
+            .filter(|p| {
+                let Ok(zone_type) = fs::read_to_string(p.join("type")) else {
+                    return false;
+                };
+                let zone_type = zone_type.trim();
+
+                if patterns.is_empty() {
+                    self.defaults.iter().any(|s| zone_type.contains(s))
+                } else {
+                    patterns.iter().any(|s| zone_type.contains(s.as_str()))
+                }
+            })
+            .map(|p| p.join("temp"))
+            .collect();
+
+        info!("{} thermal zones selected: {}", self.name, zones.len());
+        zones
+    }
+}
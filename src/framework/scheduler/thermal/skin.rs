@@ -0,0 +1,139 @@
+/* Copyright 2023 shadow3aaa@gitbub.com
+*
+*  Licensed under the Apache License, Version 2.0 (the "License");
+*  you may not use this file except in compliance with the License.
+*  You may obtain a copy of the License at
+*
+*      http://www.apache.org/licenses/LICENSE-2.0
+*
+*  Unless required by applicable law or agreed to in writing, software
+*  distributed under the License is distributed on an "AS IS" BASIS,
+*  WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+*  See the License for the specific language governing permissions and
+*  limitations under the License. */
+use std::time::{Duration, Instant};
+
+const DWELL_TIME: Duration = Duration::from_secs(10);
+const HYSTERESIS: f64 = 2.0; // 单位: ℃
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FpsStep {
+    Down,
+    Up,
+}
+
+// 外层慢速控制环: 机身温度持续高于设定点时降低目标帧率, 持续低于(设定点 - 回差)时恢复
+pub struct SkinGovernor {
+    hot_since: Option<Instant>,
+    cool_since: Option<Instant>,
+}
+
+impl SkinGovernor {
+    pub const fn new() -> Self {
+        Self {
+            hot_since: None,
+            cool_since: None,
+        }
+    }
+
+    pub fn update(&mut self, temp: Option<f64>, setpoint: Option<f64>) -> Option<FpsStep> {
+        let (Some(temp), Some(setpoint)) = (temp, setpoint) else {
+            self.reset();
+            return None;
+        };
+
+        if temp > setpoint {
+            self.cool_since = None;
+            let hot_since = *self.hot_since.get_or_insert_with(Instant::now);
+
+            if hot_since.elapsed() >= DWELL_TIME {
+                self.hot_since = None;
+                return Some(FpsStep::Down);
+            }
+        } else if temp < setpoint - HYSTERESIS {
+            self.hot_since = None;
+            let cool_since = *self.cool_since.get_or_insert_with(Instant::now);
+
+            if cool_since.elapsed() >= DWELL_TIME {
+                self.cool_since = None;
+                return Some(FpsStep::Up);
+            }
+        } else {
+            self.reset();
+        }
+
+        None
+    }
+
+    pub const fn reset(&mut self) {
+        self.hot_since = None;
+        self.cool_since = None;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SETPOINT: Option<f64> = Some(40.0);
+
+    // 模拟经过elapsed后读到temp
+    fn step(governor: &mut SkinGovernor, temp: f64, elapsed: Duration) -> Option<FpsStep> {
+        for since in [&mut governor.hot_since, &mut governor.cool_since]
+            .into_iter()
+            .flatten()
+        {
+            *since = since.checked_sub(elapsed).unwrap();
+        }
+
+        governor.update(Some(temp), SETPOINT)
+    }
+
+    #[test]
+    fn step_down() {
+        let mut governor = SkinGovernor::new();
+
+        assert_eq!(step(&mut governor, 41.0, Duration::ZERO), None);
+        assert_eq!(step(&mut governor, 41.0, DWELL_TIME / 2), None);
+        assert_eq!(
+            step(&mut governor, 41.0, DWELL_TIME / 2),
+            Some(FpsStep::Down)
+        );
+
+        // 降档后重新计时
+        assert_eq!(step(&mut governor, 41.0, Duration::ZERO), None);
+        assert_eq!(step(&mut governor, 41.0, DWELL_TIME), Some(FpsStep::Down));
+    }
+
+    #[test]
+    fn step_up() {
+        let mut governor = SkinGovernor::new();
+
+        // 回差以内不恢复
+        assert_eq!(step(&mut governor, 39.0, Duration::ZERO), None);
+        assert_eq!(step(&mut governor, 39.0, DWELL_TIME * 2), None);
+
+        assert_eq!(step(&mut governor, 37.5, Duration::ZERO), None);
+        assert_eq!(step(&mut governor, 37.5, DWELL_TIME), Some(FpsStep::Up));
+    }
+
+    #[test]
+    fn no_flap() {
+        let mut governor = SkinGovernor::new();
+
+        // 在设定点附近来回波动不会累积停留时间
+        for _ in 0..10 {
+            assert_eq!(step(&mut governor, 41.0, DWELL_TIME / 2), None);
+            assert_eq!(step(&mut governor, 41.0, DWELL_TIME / 3), None);
+            assert_eq!(step(&mut governor, 39.0, DWELL_TIME / 2), None);
+            assert_eq!(step(&mut governor, 37.0, DWELL_TIME / 2), None);
+        }
+
+        // 没有读数或者没有设定点时重置
+        assert_eq!(step(&mut governor, 41.0, DWELL_TIME / 2), None);
+        assert_eq!(governor.update(None, SETPOINT), None);
+        assert_eq!(step(&mut governor, 41.0, DWELL_TIME / 2), None);
+        assert_eq!(governor.update(Some(41.0), None), None);
+        assert_eq!(step(&mut governor, 41.0, DWELL_TIME / 2), None);
+    }
+}
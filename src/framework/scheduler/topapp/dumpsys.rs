@@ -0,0 +1,104 @@
+/* Copyright 2023 shadow3aaa@gitbub.com
+*
+*  Licensed under the Apache License, Version 2.0 (the "License");
+*  you may not use this file except in compliance with the License.
+*  You may obtain a copy of the License at
+*
+*      http://www.apache.org/licenses/LICENSE-2.0
+*
+*  Unless required by applicable law or agreed to in writing, software
+*  distributed under the License is distributed on an "AS IS" BASIS,
+*  WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+*  See the License for the specific language governing permissions and
+*  limitations under the License. */
+use std::process::Command;
+
+// 最后的手段, 开销大, 格式随安卓版本变化
+pub fn top_pids() -> Option<Vec<i32>> {
+    let dump = Command::new("dumpsys")
+        .args(["window", "visible-apps"])
+        .output()
+        .ok()?;
+    let dump = String::from_utf8_lossy(&dump.stdout);
+
+    Some(parse_top_app(&dump))
+}
+
+// 各版本中都有"Session{<hash> <pid>:<uid>}", 但是所在的列不同
+pub fn parse_top_app(dump: &str) -> Vec<i32> {
+    let mut pids: Vec<i32> = dump
+        .lines()
+        .filter_map(|l| l.split_once("Session{"))
+        .filter_map(|(_, session)| session.split_whitespace().nth(1))
+        .filter_map(|s| s.split(':').next())
+        .filter_map(|p| p.trim().parse().ok())
+        .collect();
+
+    pids.sort_unstable();
+    pids.dedup();
+    pids
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ANDROID_9: &str = "WINDOW MANAGER WINDOWS (dumpsys window windows)
+  Window #3 Window{3e1b2c0 u0 com.miHoYo.Yuanshen/com.miHoYo.GetMobileInfo.MainActivity}:
+    mDisplayId=0 stackId=12 mSession=Session{a1b2c3d 12345:u0a257} mClient=android.os.BinderProxy@1
+    mOwnerUid=10257 mShowToOwnerOnly=true package=com.miHoYo.Yuanshen appop=NONE
+";
+
+    const ANDROID_10: &str = "WINDOW MANAGER WINDOWS (dumpsys window visible-apps)
+  Window #2 Window{8c7d6e5 u0 com.tencent.tmgp.pubgmhd/com.epicgames.ue4.SplashActivity}:
+    mDisplayId=0 stackId=7 mSession=Session{1f2e3d4 8123:u0a188} mClient=android.os.BinderProxy@9a8b7c6
+    mOwnerUid=10188 mShowToOwnerOnly=true package=com.tencent.tmgp.pubgmhd appop=NONE
+    mAttrs={(0,0)(fillxfill) sim={adjust=pan forwardNavigation} ty=BASE_APPLICATION fmt=TRANSPARENT}
+";
+
+    const ANDROID_12: &str = "WINDOW MANAGER WINDOWS (dumpsys window visible-apps)
+  Window #4 Window{6b5a4c3 u0 com.miHoYo.hkrpg/com.mihoyo.combosdk.ComboSDKActivity}:
+    mDisplayId=0 rootTaskId=25 mSession=Session{4a3b2c1 15678:u0a276} mClient=android.os.BinderProxy@e2f1a0b
+    mOwnerUid=10276 showForAllUsers=false package=com.miHoYo.hkrpg appop=NONE
+";
+
+    const ANDROID_13: &str = "WINDOW MANAGER WINDOWS (dumpsys window visible-apps)
+  Window #5 Window{9f0e1d2 u0 com.tencent.tmgp.sgame/com.tencent.tmgp.sgame.SGameActivity}:
+    mDisplayId=0 rootTaskId=31 mSession=Session{5c4d3e2 23456:u0a301} mClient=android.os.BinderProxy@2
+  Window #6 Window{00aa11 u0 com.android.systemui/SplitScreen}:
+    mDisplayId=0 rootTaskId=32 mSession=Session{77 3456:u0a12} mClient=android.os.BinderProxy@3
+";
+
+    const ANDROID_14: &str = "WINDOW MANAGER WINDOWS (dumpsys window visible-apps)
+  Window #7 Window{c1d2e3f u0 com.miHoYo.Yuanshen/com.miHoYo.GetMobileInfo.MainActivity}:
+    mDisplayId=0 rootTaskId=41 mSession=Session{d4e5f6a 9876:u0a288} mClient=android.os.BinderProxy@b1c2d3e
+    mOwnerUid=10288 showForAllUsers=false package=com.miHoYo.Yuanshen appop=NONE
+    mViewVisibility=0x0 mHaveFrame=true mObscured=false
+  Window #8 Window{e5f6a7b u0 com.miHoYo.Yuanshen/com.miHoYo.GetMobileInfo.MainActivity}:
+    mDisplayId=0 rootTaskId=41 mSession=Session{d4e5f6a 9876:u0a288} mClient=android.os.BinderProxy@c2d3e4f
+";
+
+    #[test]
+    fn android_versions() {
+        assert_eq!(parse_top_app(ANDROID_9), [12345]);
+        assert_eq!(parse_top_app(ANDROID_10), [8123]);
+        assert_eq!(parse_top_app(ANDROID_12), [15678]);
+        assert_eq!(parse_top_app(ANDROID_13), [3456, 23456]);
+        // 同一进程的多个窗口只保留一次
+        assert_eq!(parse_top_app(ANDROID_14), [9876]);
+    }
+
+    #[test]
+    fn malformed() {
+        let dump = "    mSession=Session{deadbeef 4567:1000}
+ Session{broken
+ Session{x notapid:u0a1}
+ garbage Session{}
+ Session{abc -}
+";
+
+        assert_eq!(parse_top_app(dump), [4567]);
+        assert!(parse_top_app("").is_empty());
+        assert!(parse_top_app("WINDOW MANAGER WINDOWS (dumpsys window visible-apps)\n").is_empty());
+    }
+}
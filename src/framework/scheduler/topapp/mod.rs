@@ -0,0 +1,256 @@
+/* Copyright 2023 shadow3aaa@gitbub.com
+*
+*  Licensed under the Apache License, Version 2.0 (the "License");
+*  you may not use this file except in compliance with the License.
+*  You may obtain a copy of the License at
+*
+*      http://www.apache.org/licenses/LICENSE-2.0
+*
+*  Unless required by applicable law or agreed to in writing, software
+*  distributed under the License is distributed on an "AS IS" BASIS,
+*  WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+*  See the License for the specific language governing permissions and
+*  limitations under the License. */
+mod dumpsys;
+
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    time::{Duration, Instant},
+};
+
+use inotify::{Inotify, WatchMask};
+use log::info;
+
+const REFRESH_TIME: Duration = Duration::from_secs(1);
+const CGROUP_PROCS: [&str; 2] = [
+    "dev/cpuset/top-app/cgroup.procs",
+    "dev/stune/top-app/cgroup.procs",
+];
+const DUMPSYS: &str = "system/bin/dumpsys";
+const FOREGROUND_APP_ADJ: i32 = 0;
+
+#[derive(Debug)]
+enum Source {
+    // cgroupfs不一定为cgroup.procs产生inotify事件, 所以仍然定时重读
+    Cgroup {
+        path: PathBuf,
+        inotify: Option<Inotify>,
+    },
+    Dumpsys,
+    OomScore,
+}
+
+impl Source {
+    // 按开销从小到大: cgroup -> dumpsys -> oom_score_adj(只能逐个pid查询)
+    fn detect<P: AsRef<Path>>(root: P) -> Self {
+        let root = root.as_ref();
+
+        if let Some(path) = CGROUP_PROCS
+            .iter()
+            .map(|p| root.join(p))
+            .find(|p| fs::read_to_string(p).is_ok())
+        {
+            info!("Top-app source: {}", path.display());
+
+            let inotify = Inotify::init().ok().and_then(|inotify| {
+                inotify.watches().add(&path, WatchMask::MODIFY).ok()?;
+                Some(inotify)
+            });
+
+            return Self::Cgroup { path, inotify };
+        }
+
+        if root.join(DUMPSYS).exists() {
+            info!("Top-app source: dumpsys");
+            return Self::Dumpsys;
+        }
+
+        info!("Top-app source: oom_score_adj");
+        Self::OomScore
+    }
+
+    fn changed(&mut self) -> bool {
+        let Self::Cgroup {
+            inotify: Some(inotify),
+            ..
+        } = self
+        else {
+            return false;
+        };
+
+        let mut buffer = [0; 1024];
+        inotify
+            .read_events(&mut buffer)
+            .is_ok_and(|mut events| events.next().is_some())
+    }
+
+    // None表示只能逐个pid查询, 返回的pid已排序
+    fn top_pids(&self) -> Option<Vec<i32>> {
+        let mut pids = match self {
+            Self::Cgroup { path, .. } => Some(
+                fs::read_to_string(path)
+                    .ok()
+                    .map(|procs| {
+                        procs
+                            .lines()
+                            .filter_map(|p| p.trim().parse().ok())
+                            .collect()
+                    })
+                    .or_else(dumpsys::top_pids)
+                    .unwrap_or_default(),
+            ),
+            Self::OomScore => None,
+            Self::Dumpsys => Some(dumpsys::top_pids().unwrap_or_default()),
+        }?;
+
+        pids.sort_unstable();
+        Some(pids)
+    }
+}
+
+pub struct TopAppWatcher {
+    source: Source,
+    top_pids: Option<Vec<i32>>,
+    checked: Vec<(i32, bool)>,
+    last_refresh: Instant,
+    changed: bool,
+}
+
+impl TopAppWatcher {
+    pub fn new() -> Self {
+        Self::from_root("/")
+    }
+
+    pub fn from_root<P: AsRef<Path>>(root: P) -> Self {
+        let source = Source::detect(root);
+        let top_pids = source.top_pids();
+
+        Self {
+            source,
+            top_pids,
+            checked: Vec::new(),
+            last_refresh: Instant::now(),
+            changed: false,
+        }
+    }
+
+    pub fn is_topapp(&mut self, pid: i32) -> bool {
+        if self.last_refresh.elapsed() > REFRESH_TIME || self.source.changed() {
+            self.refresh();
+        }
+
+        if let Some(pids) = &self.top_pids {
+            return pids.contains(&pid);
+        }
+
+        if let Some((_, top)) = self.checked.iter().find(|(p, _)| *p == pid) {
+            return *top;
+        }
+
+        let top = Self::oom_score_adj(pid) == Some(FOREGROUND_APP_ADJ);
+        self.checked.push((pid, top));
+        top
+    }
+
+    fn refresh(&mut self) {
+        let top_pids = self.source.top_pids();
+        self.changed |= top_pids != self.top_pids;
+        self.top_pids = top_pids;
+        self.last_refresh = Instant::now();
+
+        if self.top_pids.is_some() {
+            self.checked.clear();
+            return;
+        }
+
+        // 只能逐个pid查询时重新检查查询过的pid, 有任意一个变化就报告变化
+        for (pid, top) in &mut self.checked {
+            let now = Self::oom_score_adj(*pid) == Some(FOREGROUND_APP_ADJ);
+            self.changed |= now != *top;
+            *top = now;
+        }
+    }
+
+    // 上次调用后top-app是否变化过
+    pub fn take_changed(&mut self) -> bool {
+        std::mem::take(&mut self.changed)
+    }
+
+    fn oom_score_adj(pid: i32) -> Option<i32> {
+        fs::read_to_string(format!("/proc/{pid}/oom_score_adj"))
+            .ok()?
+            .trim()
+            .parse()
+            .ok()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::misc::Fixture;
+
+    #[test]
+    fn detect_order() {
+        let fixture = Fixture::new("topapp-detect");
+        fixture.write("proc/self/oom_score_adj", "0");
+        assert!(matches!(Source::detect(fixture.path()), Source::OomScore));
+
+        fixture.write(DUMPSYS, "");
+        assert!(matches!(Source::detect(fixture.path()), Source::Dumpsys));
+
+        fixture.write("dev/stune/top-app/cgroup.procs", "1234\n");
+        match Source::detect(fixture.path()) {
+            Source::Cgroup { path, .. } => {
+                assert_eq!(path, fixture.path().join(CGROUP_PROCS[1]));
+            }
+            source => panic!("unexpected source: {source:?}"),
+        }
+    }
+
+    #[test]
+    fn cgroup_change() {
+        let fixture = Fixture::new("topapp-cgroup");
+        fixture.write(CGROUP_PROCS[0], "300\n100\n");
+
+        let mut watcher = TopAppWatcher::from_root(fixture.path());
+
+        assert!(watcher.is_topapp(100));
+        assert!(!watcher.is_topapp(200));
+
+        // 顺序变化不算变化
+        fixture.write(CGROUP_PROCS[0], "100\n300\n");
+        watcher.refresh();
+        assert!(!watcher.take_changed());
+
+        fixture.write(CGROUP_PROCS[0], "200\n");
+        watcher.refresh();
+        assert!(watcher.take_changed());
+        assert!(!watcher.take_changed());
+        assert!(watcher.is_topapp(200));
+    }
+
+    #[test]
+    fn oom_score_change() {
+        let mut watcher = TopAppWatcher {
+            source: Source::OomScore,
+            top_pids: None,
+            checked: Vec::new(),
+            last_refresh: Instant::now(),
+            changed: false,
+        };
+
+        // 本进程不是前台应用, 伪造一个之前查询到的结果
+        let pid = i32::try_from(std::process::id()).unwrap();
+        let top = TopAppWatcher::oom_score_adj(pid) == Some(FOREGROUND_APP_ADJ);
+        watcher.checked.push((pid, top));
+        watcher.refresh();
+        assert!(!watcher.take_changed());
+
+        watcher.checked[0].1 = !top;
+        watcher.refresh();
+        assert!(watcher.take_changed());
+        assert_eq!(watcher.checked, [(pid, top)]);
+    }
+}
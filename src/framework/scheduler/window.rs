@@ -0,0 +1,269 @@
+/* Copyright 2023 shadow3aaa@gitbub.com
+*
+*  Licensed under the Apache License, Version 2.0 (the "License");
+*  you may not use this file except in compliance with the License.
+*  You may obtain a copy of the License at
+*
+*      http://www.apache.org/licenses/LICENSE-2.0
+*
+*  Unless required by applicable law or agreed to in writing, software
+*  distributed under the License is distributed on an "AS IS" BASIS,
+*  WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+*  See the License for the specific language governing permissions and
+*  limitations under the License. */
+use std::{
+    collections::HashMap,
+    process::Command,
+    time::{Duration, Instant},
+};
+
+// dumpsys开销大, 主要在top-app变化后读取, 定时读取只作为兜底
+const REFRESH_TIME: Duration = Duration::from_secs(30);
+const SETTLE_TIME: Duration = Duration::from_secs(1);
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum WindowMode {
+    #[default]
+    Fullscreen,
+    Split,
+    Freeform,
+    Pip,
+}
+
+impl WindowMode {
+    // 安卓10/11的分屏是split-screen-*, 12以后是multi-window
+    fn parse(mode: &str) -> Option<Self> {
+        Some(match mode {
+            "fullscreen" => Self::Fullscreen,
+            "multi-window" | "split-screen-primary" | "split-screen-secondary" => Self::Split,
+            "freeform" => Self::Freeform,
+            "pinned" => Self::Pip,
+            _ => return None,
+        })
+    }
+
+    // 一个进程有多个窗口时取最显眼的那个
+    const fn rank(self) -> u8 {
+        match self {
+            Self::Fullscreen => 0,
+            Self::Split => 1,
+            Self::Freeform => 2,
+            Self::Pip => 3,
+        }
+    }
+}
+
+impl ToString for WindowMode {
+    fn to_string(&self) -> String {
+        match self {
+            Self::Fullscreen => "fullscreen",
+            Self::Split => "split",
+            Self::Freeform => "freeform",
+            Self::Pip => "pip",
+        }
+        .into()
+    }
+}
+
+pub struct WindowWatcher {
+    modes: HashMap<i32, WindowMode>,
+    last_refresh: Instant,
+    pending: Option<Instant>,
+}
+
+impl WindowWatcher {
+    pub fn new() -> Self {
+        Self {
+            modes: HashMap::new(),
+            last_refresh: Instant::now(),
+            pending: Some(Instant::now()),
+        }
+    }
+
+    // 没有窗口信息时按全屏处理
+    pub fn mode(&self, pid: i32) -> WindowMode {
+        self.modes.get(&pid).copied().unwrap_or_default()
+    }
+
+    // 返回窗口模式是否有变化
+    pub fn update(&mut self, top_changed: bool) -> bool {
+        if !self.should_refresh(top_changed) {
+            return false;
+        }
+
+        let Some(modes) = Self::read() else {
+            return false;
+        };
+
+        if modes == self.modes {
+            return false;
+        }
+
+        self.modes = modes;
+        true
+    }
+
+    // top-app变化时窗口动画可能还没结束, 等待一会再读取
+    fn should_refresh(&mut self, top_changed: bool) -> bool {
+        if top_changed {
+            self.pending = Some(Instant::now());
+        }
+
+        if self.pending.is_some_and(|t| t.elapsed() >= SETTLE_TIME)
+            || self.last_refresh.elapsed() >= REFRESH_TIME
+        {
+            self.pending = None;
+            self.last_refresh = Instant::now();
+            return true;
+        }
+
+        false
+    }
+
+    fn read() -> Option<HashMap<i32, WindowMode>> {
+        let dump = Command::new("dumpsys")
+            .args(["window", "-a", "visible-apps"])
+            .output()
+            .ok()?;
+        let dump = String::from_utf8_lossy(&dump.stdout);
+
+        Some(Self::parse_window(&dump))
+    }
+
+    //   Window #5 Window{9f0e1d2 u0 com.pkg/.MainActivity}:
+    //     mDisplayId=0 rootTaskId=31 mSession=Session{5c4d3e2 23456:u0a301} ...
+    //     mFullConfiguration={... winConfig={ ... mWindowingMode=fullscreen mDisplayWindowingMode=fullscreen ...}}
+    fn parse_window(dump: &str) -> HashMap<i32, WindowMode> {
+        let mut modes: HashMap<i32, WindowMode> = HashMap::new();
+        let mut pid = None;
+        let mut found = false;
+
+        for line in dump.lines() {
+            if line.trim_start().starts_with("Window #") {
+                pid = None;
+                found = false;
+            }
+
+            if let Some((_, session)) = line.split_once("Session{") {
+                pid = session
+                    .split_whitespace()
+                    .nth(1)
+                    .and_then(|s| s.split(':').next())
+                    .and_then(|p| p.parse().ok());
+            }
+
+            // 只取每个窗口的第一个mWindowingMode(mFullConfiguration)
+            let Some(p) = pid else {
+                continue;
+            };
+            if found {
+                continue;
+            }
+
+            let Some((_, rest)) = line.split_once("mWindowingMode=") else {
+                continue;
+            };
+            let Some(mode) = rest.split_whitespace().next().and_then(WindowMode::parse) else {
+                continue;
+            };
+
+            found = true;
+            modes
+                .entry(p)
+                .and_modify(|m| {
+                    if mode.rank() < m.rank() {
+                        *m = mode;
+                    }
+                })
+                .or_insert(mode);
+        }
+
+        modes
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Android 13 分屏 + 全屏
+    const ANDROID_13: &str = "WINDOW MANAGER WINDOWS (dumpsys window visible-apps)
+  Window #4 Window{9f0e1d2 u0 com.tencent.tmgp.sgame/com.tencent.tmgp.sgame.SGameActivity}:
+    mDisplayId=0 rootTaskId=31 mSession=Session{5c4d3e2 23456:u0a301} mClient=android.os.BinderProxy@2
+    mOwnerUid=10301 showForAllUsers=false package=com.tencent.tmgp.sgame appop=NONE
+    mFullConfiguration={1.0 460mcc1mnc [zh_CN] ldltr sw392dp w392dp h429dp 440dpi nrml long port night finger -keyb/v/h -nav/h winConfig={ mBounds=Rect(0, 0 - 1080, 1178) mAppBounds=Rect(0, 0 - 1080, 1178) mMaxBounds=Rect(0, 0 - 1080, 2400) mDisplayRotation=ROTATION_0 mWindowingMode=multi-window mDisplayWindowingMode=fullscreen mActivityType=standard mAlwaysOnTop=undefined mRotation=ROTATION_0} s.6 fontWeightAdjustment=0}
+    mLastReportedConfiguration={1.0 winConfig={ mWindowingMode=fullscreen mDisplayWindowingMode=fullscreen}}
+  Window #5 Window{aa1b2c3 u0 com.miHoYo.Yuanshen/com.miHoYo.GetMobileInfo.MainActivity}:
+    mDisplayId=0 rootTaskId=32 mSession=Session{7f8e9d0 34567:u0a257} mClient=android.os.BinderProxy@3
+    mFullConfiguration={1.0 winConfig={ mBounds=Rect(0, 0 - 2400, 1080) mWindowingMode=fullscreen mDisplayWindowingMode=fullscreen mActivityType=standard}}
+";
+
+    // Android 10 分屏, 同一进程还有一个画中画窗口
+    const ANDROID_10: &str = "WINDOW MANAGER WINDOWS (dumpsys window visible-apps)
+  Window #2 Window{1a2b3c4 u0 com.tencent.tmgp.pubgmhd/com.epicgames.ue4.GameActivity}:
+    mDisplayId=0 stackId=5 mSession=Session{2d3e4f5 1111:u0a10} mClient=android.os.BinderProxy@4
+    mFullConfiguration={1.0 winConfig={ mBounds=Rect(0, 0 - 1080, 1100) mWindowingMode=split-screen-primary mDisplayWindowingMode=fullscreen}}
+  Window #3 Window{5e6f7a8 u0 com.tencent.tmgp.pubgmhd/PopupWindow}:
+    mDisplayId=0 stackId=6 mSession=Session{2d3e4f5 1111:u0a10} mClient=android.os.BinderProxy@5
+    mFullConfiguration={1.0 winConfig={ mWindowingMode=pinned mDisplayWindowingMode=fullscreen}}
+";
+
+    // 画中画, 自由窗口, 没有配置的窗口和损坏的session
+    const MIXED: &str = "  Window #1 Window{1 u0 com.video/.PlayerActivity}:
+    mSession=Session{9 2222:u0a11} mClient=android.os.BinderProxy@6
+    mFullConfiguration={winConfig={ mWindowingMode=pinned mDisplayWindowingMode=fullscreen}}
+  Window #2 Window{2 u0 com.game/.MainActivity}:
+    mSession=Session{9 3333:u0a12} mClient=android.os.BinderProxy@7
+    mFullConfiguration={winConfig={ mWindowingMode=freeform mDisplayWindowingMode=fullscreen}}
+  Window #3 Window{3 u0 StatusBar}:
+    mSession=Session{9 4444:1000} mClient=android.os.BinderProxy@8
+  Window #4 Window{4 u0 Broken}:
+    mSession=Session{broken
+    mFullConfiguration={winConfig={ mWindowingMode=freeform}}
+  Window #5 Window{5 u0 com.unknown/.MainActivity}:
+    mSession=Session{9 5555:u0a13} mClient=android.os.BinderProxy@9
+    mFullConfiguration={winConfig={ mWindowingMode=undefined}}
+";
+
+    fn mode(dump: &str, pid: i32) -> Option<WindowMode> {
+        WindowWatcher::parse_window(dump).get(&pid).copied()
+    }
+
+    #[test]
+    fn android_13() {
+        assert_eq!(mode(ANDROID_13, 23456), Some(WindowMode::Split));
+        assert_eq!(mode(ANDROID_13, 34567), Some(WindowMode::Fullscreen));
+    }
+
+    #[test]
+    fn android_10() {
+        // 取最显眼的窗口
+        assert_eq!(mode(ANDROID_10, 1111), Some(WindowMode::Split));
+    }
+
+    #[test]
+    fn mixed() {
+        assert_eq!(mode(MIXED, 2222), Some(WindowMode::Pip));
+        assert_eq!(mode(MIXED, 3333), Some(WindowMode::Freeform));
+        assert_eq!(mode(MIXED, 4444), None);
+        assert_eq!(mode(MIXED, 5555), None);
+        assert_eq!(WindowWatcher::parse_window(MIXED).len(), 2);
+        assert!(WindowWatcher::parse_window("").is_empty());
+    }
+
+    #[test]
+    fn refresh_on_top_change() {
+        let mut watcher = WindowWatcher::new();
+        assert!(!watcher.should_refresh(false));
+
+        // top-app变化后等待窗口稳定再读取
+        assert!(!watcher.should_refresh(true));
+        watcher.pending = Instant::now().checked_sub(SETTLE_TIME);
+        assert!(watcher.should_refresh(false));
+        assert!(!watcher.should_refresh(false));
+
+        watcher.last_refresh = Instant::now().checked_sub(REFRESH_TIME).unwrap();
+        assert!(watcher.should_refresh(false));
+        assert!(!watcher.should_refresh(false));
+    }
+}
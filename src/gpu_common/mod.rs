@@ -0,0 +1,208 @@
+/* Copyright 2023 shadow3aaa@gitbub.com
+*
+*  Licensed under the Apache License, Version 2.0 (the "License");
+*  you may not use this file except in compliance with the License.
+*  You may obtain a copy of the License at
+*
+*      http://www.apache.org/licenses/LICENSE-2.0
+*
+*  Unless required by applicable law or agreed to in writing, software
+*  distributed under the License is distributed on an "AS IS" BASIS,
+*  WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+*  See the License for the specific language governing permissions and
+*  limitations under the License. */
+use std::{
+    cell::Cell,
+    ffi::OsStr,
+    fs,
+    path::{Path, PathBuf},
+};
+
+use anyhow::Result;
+use likely_stable::LikelyOption;
+
+use crate::{
+    error::Error,
+    framework::{prelude::*, Result as FrameworkResult},
+    misc::{lock_write, unlock_write},
+};
+
+pub type Freq = usize; // 单位: hz
+
+const KGSL_DEVFREQ: &str = "/sys/class/kgsl/kgsl-3d0/devfreq";
+const DEVFREQ: &str = "/sys/class/devfreq";
+const BUSY_NODES: [&str; 4] = [
+    "/sys/class/kgsl/kgsl-3d0/gpu_busy_percentage",
+    "/sys/kernel/gpu/gpu_busy",
+    "/sys/module/ged/parameters/gpu_loading",
+    "/sys/kernel/ged/hal/gpu_utilization",
+];
+
+#[derive(Debug)]
+pub struct GpuCommon {
+    devfreq: PathBuf,
+    busy: Option<PathBuf>,
+    freqs: Vec<Freq>,
+    fas_freq: Cell<Freq>,
+    fas_boost: Cell<bool>,
+    in_game: Cell<bool>,
+    ceiling: Cell<f64>,
+    default_freqs: (Freq, Freq),
+}
+
+impl GpuCommon {
+    pub fn new() -> Result<Self> {
+        let devfreq = Self::find_devfreq().ok_or(Error::Other("No gpu devfreq found"))?;
+
+        let mut freqs: Vec<Freq> = fs::read_to_string(devfreq.join("available_frequencies"))?
+            .split_whitespace()
+            .filter_map(|f| f.parse().ok())
+            .collect();
+        freqs.sort_unstable();
+        freqs.dedup();
+
+        let last_freq = freqs
+            .last()
+            .copied()
+            .ok_or(Error::Other("Empty gpu frequency table"))?;
+
+        let default_freqs = (
+            Self::read_freq(devfreq.join("min_freq")).unwrap_or(freqs[0]),
+            Self::read_freq(devfreq.join("max_freq")).unwrap_or(last_freq),
+        );
+
+        let busy = BUSY_NODES
+            .iter()
+            .map(Path::new)
+            .find(|p| p.exists())
+            .map(Path::to_path_buf);
+
+        Ok(Self {
+            devfreq,
+            busy,
+            freqs,
+            fas_freq: Cell::new(last_freq),
+            fas_boost: Cell::new(false),
+            in_game: Cell::new(false),
+            ceiling: Cell::new(1.0),
+            default_freqs,
+        })
+    }
+
+    fn find_devfreq() -> Option<PathBuf> {
+        let kgsl = Path::new(KGSL_DEVFREQ);
+        if kgsl.join("available_frequencies").exists() {
+            return Some(kgsl.to_path_buf());
+        }
+
+        fs::read_dir(DEVFREQ)
+            .ok()?
+            .filter_map(|d| Some(d.ok()?.path()))
+            .filter(|p| p.join("available_frequencies").exists())
+            .find(|p| {
+                p.file_name()
+                    .and_then_likely(OsStr::to_str)
+                    .is_some_and(|n| ["gpu", "mali", "kgsl"].iter().any(|g| n.contains(g)))
+            })
+    }
+
+    fn read_freq<P: AsRef<Path>>(p: P) -> Option<Freq> {
+        fs::read_to_string(p).ok()?.trim().parse().ok()
+    }
+
+    fn set_fas_freq(&self, f: Freq) -> Result<()> {
+        self.fas_freq.set(f);
+
+        let first_freq = self.freqs[0];
+        let last_freq = self.freqs.last().copied().unwrap();
+
+        // devfreq要求min_freq <= max_freq, 先放开再收紧
+        if self.fas_boost.get() {
+            lock_write(self.devfreq.join("max_freq"), last_freq.to_string())?;
+            lock_write(self.devfreq.join("min_freq"), f.to_string())?;
+        } else {
+            lock_write(self.devfreq.join("min_freq"), first_freq.to_string())?;
+            lock_write(self.devfreq.join("max_freq"), f.to_string())?;
+        }
+
+        Ok(())
+    }
+
+    // 温控上限对应的最高fas频率
+    fn max_freq(&self) -> Freq {
+        let pos = (self.ceiling.get() * (self.freqs.len() - 1) as f64).floor() as usize;
+        self.freqs[pos]
+    }
+
+    fn step_freq(&self, up: bool) -> Freq {
+        let current_freq = self.fas_freq.get();
+        let pos = self.freqs.partition_point(|f| *f < current_freq);
+
+        if up {
+            self.freqs
+                .get(pos + 1)
+                .copied()
+                .unwrap_or(current_freq)
+                .min(self.max_freq())
+        } else {
+            self.freqs[pos.saturating_sub(1)]
+        }
+    }
+}
+
+impl PerformanceController for GpuCommon {
+    fn limit(&self, _m: Mode, _c: &Config) -> FrameworkResult<()> {
+        let _ = self.set_fas_freq(self.step_freq(false));
+        Ok(())
+    }
+
+    fn release(&self, _m: Mode, _c: &Config) -> FrameworkResult<()> {
+        let _ = self.set_fas_freq(self.step_freq(true));
+        Ok(())
+    }
+
+    fn release_max(&self, _m: Mode, _c: &Config) -> FrameworkResult<()> {
+        let _ = self.set_fas_freq(self.max_freq());
+        Ok(())
+    }
+
+    fn init_game(&self, m: Mode, c: &Config) -> FrameworkResult<()> {
+        self.fas_boost.set(c.mode_config(m).fas_boost);
+        self.in_game.set(true);
+
+        let _ = self.set_fas_freq(self.max_freq());
+        Ok(())
+    }
+
+    fn init_default(&self, _m: Mode, _c: &Config) -> FrameworkResult<()> {
+        let (min_freq, max_freq) = self.default_freqs;
+        self.fas_freq.set(self.freqs.last().copied().unwrap());
+        self.in_game.set(false);
+
+        unlock_write(self.devfreq.join("max_freq"), max_freq.to_string())?;
+        unlock_write(self.devfreq.join("min_freq"), min_freq.to_string())?;
+
+        Ok(())
+    }
+
+    fn set_ceiling(&self, ceiling: f64) {
+        self.ceiling.set(ceiling.clamp(0.0, 1.0));
+
+        let max_freq = self.max_freq();
+        if self.in_game.get() && self.fas_freq.get() > max_freq {
+            let _ = self.set_fas_freq(max_freq);
+        }
+    }
+
+    fn usage(&self) -> Option<f64> {
+        let busy = fs::read_to_string(self.busy.as_ref()?).ok()?;
+        let busy: f64 = busy
+            .split_whitespace()
+            .next()?
+            .trim_end_matches('%')
+            .parse()
+            .ok()?;
+
+        Some((busy / 100.0).clamp(0.0, 1.0))
+    }
+}
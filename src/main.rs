@@ -24,11 +24,16 @@
 #[cfg(not(target_arch = "aarch64"))]
 compile_error!("Only for aarch64 android");
 
+mod bus_common;
 mod clean;
+mod composite;
 mod cpu_common;
 mod error;
 mod framework;
+mod gpu_common;
 mod misc;
+mod selector;
+mod uclamp;
 
 use std::{env, fs, process, thread};
 
@@ -41,7 +46,12 @@ use log::{error, info, warn};
 #[cfg(debug_assertions)]
 use log::debug;
 
+use bus_common::BusCommon;
+use composite::CompositeController;
 use cpu_common::CpuCommon;
+use gpu_common::GpuCommon;
+use selector::CpuSelector;
+use uclamp::Uclamp;
 
 const USER_CONFIG: &str = "/data/media/0/Android/fas-rs/games.toml";
 
@@ -84,6 +94,22 @@ fn run<S: AsRef<str>>(std_path: S) -> Result<()> {
     #[cfg(debug_assertions)]
     debug!("{cpu:#?}");
 
+    let uclamp = Uclamp::new()
+        .map_err(|e| info!("Uclamp controller unavailable: {e}"))
+        .ok();
+    let cpu = CpuSelector::new(cpu, uclamp, BusCommon::new());
+
+    let mut controller = CompositeController::new().controller(cpu);
+    match GpuCommon::new() {
+        Ok(gpu) => {
+            #[cfg(debug_assertions)]
+            debug!("{gpu:#?}");
+
+            controller = controller.controller(gpu);
+        }
+        Err(e) => warn!("Gpu controller unavailable: {e}"),
+    }
+
     thread::Builder::new()
         .name("CleanerThead".into())
         .spawn(clean::cleaner)?;
@@ -91,7 +117,7 @@ fn run<S: AsRef<str>>(std_path: S) -> Result<()> {
 
     Scheduler::new()
         .config(config)
-        .controller(cpu)
+        .controller(controller)
         .start_run()?;
 
     Ok(())
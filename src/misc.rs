@@ -11,7 +11,7 @@
 *  WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
 *  See the License for the specific language governing permissions and
 *  limitations under the License. */
-use std::{ffi::CString, fs, path::Path, ptr};
+use std::{ffi::CString, fs, os::unix::fs::PermissionsExt, path::Path, ptr};
 
 use crate::framework::Result;
 use libc::{mount, umount, umount2, MS_BIND, MS_REC};
@@ -33,6 +33,27 @@ pub fn lock_value<P: AsRef<Path>, S: AsRef<str>>(p: P, v: S) -> Result<()> {
     Ok(())
 }
 
+pub fn lock_write<S: AsRef<str>, P: AsRef<Path>>(p: P, s: S) -> Result<()> {
+    let s = s.as_ref();
+    let p = p.as_ref();
+
+    let _ = fs::set_permissions(p, PermissionsExt::from_mode(0o644));
+    fs::write(p, s)?;
+    let _ = fs::set_permissions(p, PermissionsExt::from_mode(0o444));
+
+    Ok(())
+}
+
+pub fn unlock_write<S: AsRef<str>, P: AsRef<Path>>(p: P, s: S) -> Result<()> {
+    let s = s.as_ref();
+    let p = p.as_ref();
+
+    let _ = fs::set_permissions(p, PermissionsExt::from_mode(0o644));
+    fs::write(p, s)?;
+
+    Ok(())
+}
+
 fn mount_bind(src_path: &str, dest_path: &str) {
     let src_path = CString::new(src_path).unwrap();
     let dest_path = CString::new(dest_path).unwrap();
@@ -54,3 +75,43 @@ fn unmount(file_system: &str) {
     let path = CString::new(file_system).unwrap();
     let _result = unsafe { umount(path.as_ptr()) };
 }
+
+// 测试用的临时sysfs/procfs目录, drop时删除
+#[cfg(test)]
+pub struct Fixture {
+    root: std::path::PathBuf,
+}
+
+#[cfg(test)]
+impl Fixture {
+    pub fn new(name: &str) -> Self {
+        let root = std::env::temp_dir().join(format!("fas-rs-{name}-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&root);
+        fs::create_dir_all(&root).unwrap();
+
+        Self { root }
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.root
+    }
+
+    pub fn write<P: AsRef<Path>, S: AsRef<str>>(&self, p: P, s: S) -> std::path::PathBuf {
+        let path = self.root.join(p);
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(&path, s.as_ref()).unwrap();
+
+        path
+    }
+
+    pub fn read<P: AsRef<Path>>(&self, p: P) -> String {
+        fs::read_to_string(self.root.join(p)).unwrap()
+    }
+}
+
+#[cfg(test)]
+impl Drop for Fixture {
+    fn drop(&mut self) {
+        let _ = fs::remove_dir_all(&self.root);
+    }
+}
@@ -0,0 +1,249 @@
+/* Copyright 2023 shadow3aaa@gitbub.com
+*
+*  Licensed under the Apache License, Version 2.0 (the "License");
+*  you may not use this file except in compliance with the License.
+*  You may obtain a copy of the License at
+*
+*      http://www.apache.org/licenses/LICENSE-2.0
+*
+*  Unless required by applicable law or agreed to in writing, software
+*  distributed under the License is distributed on an "AS IS" BASIS,
+*  WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+*  See the License for the specific language governing permissions and
+*  limitations under the License. */
+use std::cell::Cell;
+
+use log::{info, warn};
+
+use crate::{
+    bus_common::BusCommon,
+    cpu_common::CpuCommon,
+    framework::{config::CpuBackend, prelude::*, Result},
+    uclamp::Uclamp,
+};
+
+// 按模式配置在cpufreq和uclamp之间切换cpu控制方式, 内存带宽跟随cpu性能等级
+#[derive(Debug)]
+pub struct CpuSelector {
+    cpufreq: CpuCommon,
+    uclamp: Option<Uclamp>,
+    bus: Option<BusCommon>,
+    active: Cell<CpuBackend>,
+}
+
+impl CpuSelector {
+    pub const fn new(cpufreq: CpuCommon, uclamp: Option<Uclamp>, bus: Option<BusCommon>) -> Self {
+        Self {
+            cpufreq,
+            uclamp,
+            bus,
+            active: Cell::new(CpuBackend::Cpufreq),
+        }
+    }
+
+    fn backend(&self, backend: CpuBackend) -> &dyn PerformanceController {
+        match (backend, &self.uclamp) {
+            (CpuBackend::Uclamp, Some(uclamp)) => uclamp,
+            _ => &self.cpufreq,
+        }
+    }
+
+    fn active(&self) -> &dyn PerformanceController {
+        self.backend(self.active.get())
+    }
+
+    fn level(&self) -> f64 {
+        match (self.active.get(), &self.uclamp) {
+            (CpuBackend::Uclamp, Some(uclamp)) => uclamp.level(),
+            _ => self.cpufreq.level(),
+        }
+    }
+
+    fn sync_bus(&self, level: f64, m: Mode, c: &Config) {
+        if let Some(ref bus) = self.bus {
+            bus.set_level(level, c.mode_config(m).fas_boost);
+        }
+    }
+
+    fn select(&self, m: Mode, c: &Config) -> Result<()> {
+        let mut backend = c.mode_config(m).cpu_backend;
+
+        if backend == CpuBackend::Uclamp && self.uclamp.is_none() {
+            warn!("Uclamp is not available, fall back to cpufreq");
+            backend = CpuBackend::Cpufreq;
+        }
+
+        if backend != self.active.get() {
+            self.active().init_default(m, c)?;
+            self.active.set(backend);
+            info!("Cpu backend switched to {backend:?}");
+        }
+
+        Ok(())
+    }
+}
+
+impl PerformanceController for CpuSelector {
+    fn limit(&self, m: Mode, c: &Config) -> Result<()> {
+        self.active().limit(m, c)?;
+        self.sync_bus(self.level(), m, c);
+        Ok(())
+    }
+
+    fn release(&self, m: Mode, c: &Config) -> Result<()> {
+        self.active().release(m, c)?;
+        self.sync_bus(self.level(), m, c);
+        Ok(())
+    }
+
+    fn release_max(&self, m: Mode, c: &Config) -> Result<()> {
+        self.active().release_max(m, c)?;
+        self.sync_bus(1.0, m, c);
+        Ok(())
+    }
+
+    fn init_game(&self, m: Mode, c: &Config) -> Result<()> {
+        self.select(m, c)?;
+        self.active().init_game(m, c)?;
+        self.sync_bus(self.level(), m, c);
+        Ok(())
+    }
+
+    fn init_default(&self, m: Mode, c: &Config) -> Result<()> {
+        if let Some(ref bus) = self.bus {
+            bus.init_default();
+        }
+
+        self.active().init_default(m, c)
+    }
+
+    fn set_ceiling(&self, ceiling: f64) {
+        self.cpufreq.set_ceiling(ceiling);
+
+        if let Some(ref uclamp) = self.uclamp {
+            uclamp.set_ceiling(ceiling);
+        }
+    }
+
+    fn status(&self) -> Vec<(String, String)> {
+        self.cpufreq.status()
+    }
+
+    fn usage(&self) -> Option<f64> {
+        self.cpufreq.usage()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use super::*;
+    use crate::{
+        bus_common::bus_nodes, cpu_common::two_clusters, misc::Fixture, uclamp::top_app_cpuctl,
+    };
+
+    const BIG_MAX: &str = "sys/devices/system/cpu/cpufreq/policy4/scaling_max_freq";
+    const UCLAMP_MAX: &str = "dev/cpuctl/top-app/cpu.uclamp.max";
+
+    // performance模式使用uclamp, 其余模式使用cpufreq
+    fn config(fixture: &Fixture) -> Config {
+        let games = fs::read_to_string("module/games.toml").unwrap();
+        let (head, tail) = games.split_once("[performance]").unwrap();
+        let tail = tail.replacen("cpu_backend = \"cpufreq\"", "cpu_backend = \"uclamp\"", 1);
+        let path = fixture.write("games.toml", format!("{head}[performance]{tail}"));
+
+        Config::new(path, "module/games.toml".into()).unwrap()
+    }
+
+    #[test]
+    fn switch_backend() {
+        let cpu_fixture = two_clusters("selector-cpu");
+        let uclamp_fixture = top_app_cpuctl("selector-uclamp");
+        let config = config(&cpu_fixture);
+        let selector = CpuSelector::new(
+            CpuCommon::from_root(cpu_fixture.path()).unwrap(),
+            Some(Uclamp::from_root(uclamp_fixture.path()).unwrap()),
+            None,
+        );
+
+        selector.init_game(Mode::Balance, &config).unwrap();
+        assert_eq!(selector.active.get(), CpuBackend::Cpufreq);
+        assert_eq!(cpu_fixture.read(BIG_MAX), "2400000");
+        assert_eq!(uclamp_fixture.read(UCLAMP_MAX), "max\n");
+
+        // 切换前先恢复上一个后端的默认状态
+        cpu_fixture.write(BIG_MAX, "0");
+        selector.init_game(Mode::Performance, &config).unwrap();
+        assert_eq!(selector.active.get(), CpuBackend::Uclamp);
+        assert_eq!(cpu_fixture.read(BIG_MAX), "2400000");
+        assert_eq!(uclamp_fixture.read(UCLAMP_MAX), "100.00");
+
+        // 只控制当前后端
+        selector.limit(Mode::Performance, &config).unwrap();
+        assert_eq!(uclamp_fixture.read(UCLAMP_MAX), "98.00");
+        assert_eq!(cpu_fixture.read(BIG_MAX), "2400000");
+        assert!((selector.level() - 0.98).abs() < 1e-9);
+
+        selector.init_game(Mode::Balance, &config).unwrap();
+        assert_eq!(selector.active.get(), CpuBackend::Cpufreq);
+        assert_eq!(uclamp_fixture.read(UCLAMP_MAX), "max");
+    }
+
+    #[test]
+    fn fallback_without_uclamp() {
+        let cpu_fixture = two_clusters("selector-fallback");
+        let config = config(&cpu_fixture);
+
+        // 内核不支持uclamp时启动阶段得到None
+        assert!(Uclamp::from_root(cpu_fixture.path()).is_err());
+        let selector = CpuSelector::new(
+            CpuCommon::from_root(cpu_fixture.path()).unwrap(),
+            None,
+            None,
+        );
+
+        selector.init_game(Mode::Performance, &config).unwrap();
+        assert_eq!(selector.active.get(), CpuBackend::Cpufreq);
+        assert_eq!(cpu_fixture.read(BIG_MAX), "2400000");
+
+        selector.limit(Mode::Performance, &config).unwrap();
+        assert!(selector.level() < 1.0);
+        assert_ne!(cpu_fixture.read(BIG_MAX), "2400000");
+    }
+
+    #[test]
+    fn bus_follows_cpu() {
+        const LLCC: &str = "sys/class/devfreq/soc:qcom,cpu-cpu-llcc-bw";
+        const LLCC_FREQS: [&str; 4] = ["762", "2086", "4577", "6515"];
+
+        let fixture = two_clusters("selector-bus");
+        bus_nodes(&fixture);
+        let config = config(&fixture);
+        let selector = CpuSelector::new(
+            CpuCommon::from_root(fixture.path()).unwrap(),
+            None,
+            BusCommon::from_root(fixture.path()),
+        );
+        let llcc_max = || fixture.read(format!("{LLCC}/max_freq"));
+
+        selector.init_game(Mode::Balance, &config).unwrap();
+        assert_eq!(llcc_max(), "6515");
+
+        for _ in 0..30 {
+            selector.limit(Mode::Balance, &config).unwrap();
+        }
+        let level = selector.level();
+        assert!(level < 0.5);
+        let pos = (level * 3.0).ceil() as usize;
+        assert_eq!(llcc_max(), LLCC_FREQS[pos]);
+        assert_eq!(fixture.read(format!("{LLCC}/min_freq")), "762");
+
+        selector.release_max(Mode::Balance, &config).unwrap();
+        assert_eq!(llcc_max(), "6515");
+
+        fixture.write(format!("{LLCC}/max_freq"), "762");
+        selector.init_default(Mode::Balance, &config).unwrap();
+        assert_eq!(llcc_max(), "6515");
+    }
+}
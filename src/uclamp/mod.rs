@@ -0,0 +1,210 @@
+/* Copyright 2023 shadow3aaa@gitbub.com
+*
+*  Licensed under the Apache License, Version 2.0 (the "License");
+*  you may not use this file except in compliance with the License.
+*  You may obtain a copy of the License at
+*
+*      http://www.apache.org/licenses/LICENSE-2.0
+*
+*  Unless required by applicable law or agreed to in writing, software
+*  distributed under the License is distributed on an "AS IS" BASIS,
+*  WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+*  See the License for the specific language governing permissions and
+*  limitations under the License. */
+use std::{
+    cell::Cell,
+    fs,
+    path::{Path, PathBuf},
+};
+
+use anyhow::Result;
+
+use crate::{
+    error::Error,
+    framework::{prelude::*, Result as FrameworkResult},
+    misc::{lock_write, unlock_write},
+};
+
+const TOP_APP_CPUCTL: &str = "dev/cpuctl/top-app";
+const UCLAMP_STEP: f64 = 2.0; // 单位: %
+
+#[derive(Debug)]
+pub struct Uclamp {
+    path: PathBuf,
+    fas_level: Cell<f64>,
+    fas_boost: Cell<bool>,
+    in_game: Cell<bool>,
+    ceiling: Cell<f64>,
+    default_levels: (String, String),
+}
+
+impl Uclamp {
+    pub fn new() -> Result<Self> {
+        Self::from_root("/")
+    }
+
+    // root为cgroupfs所在的根目录, 方便在测试中替换
+    pub fn from_root<P: AsRef<Path>>(root: P) -> Result<Self> {
+        let path = root.as_ref().join(TOP_APP_CPUCTL);
+        let min = fs::read_to_string(path.join("cpu.uclamp.min"))
+            .map_err(|_| Error::Other("Kernel does not support uclamp"))?;
+        let max = fs::read_to_string(path.join("cpu.uclamp.max"))
+            .map_err(|_| Error::Other("Kernel does not support uclamp"))?;
+
+        Ok(Self {
+            path,
+            fas_level: Cell::new(100.0),
+            fas_boost: Cell::new(false),
+            in_game: Cell::new(false),
+            ceiling: Cell::new(1.0),
+            default_levels: (min.trim().to_string(), max.trim().to_string()),
+        })
+    }
+
+    pub fn level(&self) -> f64 {
+        self.fas_level.get() / 100.0
+    }
+
+    fn set_fas_level(&self, l: f64) -> Result<()> {
+        let level = l.clamp(0.0, self.ceiling.get() * 100.0);
+        self.fas_level.set(level);
+
+        let level = format!("{level:.2}");
+        let min_path = self.path.join("cpu.uclamp.min");
+        let max_path = self.path.join("cpu.uclamp.max");
+
+        if self.fas_boost.get() {
+            lock_write(max_path, "max")?;
+            lock_write(min_path, level)?;
+        } else {
+            lock_write(min_path, "0.00")?;
+            lock_write(max_path, level)?;
+        }
+
+        Ok(())
+    }
+}
+
+impl PerformanceController for Uclamp {
+    fn limit(&self, _m: Mode, _c: &Config) -> FrameworkResult<()> {
+        let _ = self.set_fas_level(self.fas_level.get() - UCLAMP_STEP);
+        Ok(())
+    }
+
+    fn release(&self, _m: Mode, _c: &Config) -> FrameworkResult<()> {
+        let _ = self.set_fas_level(self.fas_level.get() + UCLAMP_STEP);
+        Ok(())
+    }
+
+    fn release_max(&self, _m: Mode, _c: &Config) -> FrameworkResult<()> {
+        let _ = self.set_fas_level(100.0);
+        Ok(())
+    }
+
+    fn init_game(&self, m: Mode, c: &Config) -> FrameworkResult<()> {
+        self.fas_boost.set(c.mode_config(m).fas_boost);
+        self.in_game.set(true);
+        let _ = self.set_fas_level(100.0);
+        Ok(())
+    }
+
+    fn init_default(&self, _m: Mode, _c: &Config) -> FrameworkResult<()> {
+        let (min, max) = &self.default_levels;
+        self.fas_level.set(100.0);
+        self.in_game.set(false);
+
+        unlock_write(self.path.join("cpu.uclamp.max"), max)?;
+        unlock_write(self.path.join("cpu.uclamp.min"), min)?;
+
+        Ok(())
+    }
+
+    fn set_ceiling(&self, ceiling: f64) {
+        self.ceiling.set(ceiling.clamp(0.0, 1.0));
+
+        if self.in_game.get() && self.fas_level.get() > ceiling * 100.0 {
+            let _ = self.set_fas_level(ceiling * 100.0);
+        }
+    }
+}
+
+// 测试用的top-app cpuctl, 默认不限制
+#[cfg(test)]
+pub fn top_app_cpuctl(name: &str) -> crate::misc::Fixture {
+    let fixture = crate::misc::Fixture::new(name);
+    fixture.write(format!("{TOP_APP_CPUCTL}/cpu.uclamp.min"), "0.00\n");
+    fixture.write(format!("{TOP_APP_CPUCTL}/cpu.uclamp.max"), "max\n");
+
+    fixture
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::misc::Fixture;
+
+    const MIN: &str = "dev/cpuctl/top-app/cpu.uclamp.min";
+    const MAX: &str = "dev/cpuctl/top-app/cpu.uclamp.max";
+
+    fn levels(fixture: &Fixture) -> (String, String) {
+        (fixture.read(MIN), fixture.read(MAX))
+    }
+
+    #[test]
+    fn unsupported() {
+        let fixture = Fixture::new("uclamp-none");
+        assert!(Uclamp::from_root(fixture.path()).is_err());
+
+        fixture.write(MIN, "0.00\n");
+        assert!(Uclamp::from_root(fixture.path()).is_err());
+    }
+
+    #[test]
+    fn clamp_max() {
+        let fixture = top_app_cpuctl("uclamp-max");
+        let config = Config::new("module/games.toml", "module/games.toml").unwrap();
+        let uclamp = Uclamp::from_root(fixture.path()).unwrap();
+
+        uclamp.init_game(Mode::Balance, &config).unwrap();
+        assert_eq!(levels(&fixture), ("0.00".into(), "100.00".into()));
+
+        uclamp.limit(Mode::Balance, &config).unwrap();
+        uclamp.limit(Mode::Balance, &config).unwrap();
+        assert_eq!(levels(&fixture), ("0.00".into(), "96.00".into()));
+        assert!((uclamp.level() - 0.96).abs() < 1e-9);
+
+        uclamp.release(Mode::Balance, &config).unwrap();
+        assert_eq!(fixture.read(MAX), "98.00");
+
+        // 温控上限立即生效, 之后的提升也不会超过上限
+        uclamp.set_ceiling(0.5);
+        assert_eq!(fixture.read(MAX), "50.00");
+        uclamp.release_max(Mode::Balance, &config).unwrap();
+        assert_eq!(fixture.read(MAX), "50.00");
+
+        uclamp.set_ceiling(1.0);
+        uclamp.release_max(Mode::Balance, &config).unwrap();
+        assert_eq!(fixture.read(MAX), "100.00");
+
+        // 恢复启动时读到的值
+        uclamp.init_default(Mode::Balance, &config).unwrap();
+        assert_eq!(levels(&fixture), ("0.00".into(), "max".into()));
+    }
+
+    #[test]
+    fn boost_min() {
+        let fixture = top_app_cpuctl("uclamp-min");
+        let config = Config::new("module/games.toml", "module/games.toml").unwrap();
+        let uclamp = Uclamp::from_root(fixture.path()).unwrap();
+
+        // fas_boost时抬高下限而不是限制上限
+        uclamp.init_game(Mode::Fast, &config).unwrap();
+        assert_eq!(levels(&fixture), ("100.00".into(), "max".into()));
+
+        uclamp.limit(Mode::Fast, &config).unwrap();
+        assert_eq!(levels(&fixture), ("98.00".into(), "max".into()));
+
+        uclamp.init_default(Mode::Fast, &config).unwrap();
+        assert_eq!(levels(&fixture), ("0.00".into(), "max".into()));
+    }
+}
@@ -11,7 +11,10 @@
 *  WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
 *  See the License for the specific language governing permissions and
 *  limitations under the License. */
-use std::{collections::HashMap, time::Instant};
+use std::{
+    collections::HashMap,
+    time::{Duration, Instant, SystemTime, UNIX_EPOCH},
+};
 
 use dobby_api::Address;
 
@@ -20,8 +23,15 @@ pub struct Info {
     pub process: String,
     pub pid: i32,
     pub tid: i32,
-    pub stamps: HashMap<Address, Instant>,
+    pub stamps: HashMap<Address, Stamp>,
     pub gc_timer: Instant,
+    next_session: i64,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct Stamp {
+    pub session: i64,
+    pub instant: Instant,
 }
 
 impl Info {
@@ -30,6 +40,11 @@ impl Info {
 
         let stamps = HashMap::new();
         let gc_timer = Instant::now();
+        // 以当前时间为起点, 重启后复用了pid的进程也不会产生相同的session
+        let next_session = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_nanos() as i64;
 
         Self {
             process,
@@ -37,6 +52,40 @@ impl Info {
             tid,
             stamps,
             gc_timer,
+            next_session,
         }
     }
+
+    // 返回距上一帧的时间和这个buffer的session, 新出现的buffer分配新的session
+    pub fn stamp(&mut self, buffer: Address, instant: Instant) -> (Duration, i64) {
+        let next_session = &mut self.next_session;
+        let stamp = self.stamps.entry(buffer).or_insert_with(|| {
+            *next_session = next_session.wrapping_add(1);
+            Stamp {
+                session: *next_session,
+                instant,
+            }
+        });
+
+        let frametime = instant - stamp.instant;
+        stamp.instant = instant;
+
+        (frametime, stamp.session)
+    }
+
+    // 丢弃一段时间没有新帧的buffer, 地址被复用时会拿到新的session
+    pub fn gc(&mut self, timeout: Duration) -> Vec<(Address, i64)> {
+        self.gc_timer = Instant::now();
+
+        let mut removed = Vec::new();
+        self.stamps.retain(|buffer, stamp| {
+            let keep = stamp.instant.elapsed() < timeout;
+            if !keep {
+                removed.push((*buffer, stamp.session));
+            }
+            keep
+        });
+
+        removed
+    }
 }
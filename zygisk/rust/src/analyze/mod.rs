@@ -24,6 +24,9 @@ use log::error;
 use crate::{channel::CHANNEL, data::Data, IRemoteService::IRemoteService, IS_CHILD};
 use info::Info;
 
+const GC_INTERVAL: Duration = Duration::from_secs(1);
+const GC_TIMEOUT: Duration = Duration::from_secs(5);
+
 pub unsafe fn thread(process: String) -> Result<()> {
     let mut info = Info::new(process);
     let Some(mut fas_service) = get_server_interface() else {
@@ -44,14 +47,18 @@ pub unsafe fn thread(process: String) -> Result<()> {
         #[cfg(debug_assertions)]
         debug!("Rendering Data: {data:?}");
 
-        let last_stamp = info.stamps.entry(data.buffer).or_insert(data.instant);
-        let frametime = data.instant - *last_stamp;
-        *last_stamp = data.instant;
+        let (frametime, session) = info.stamp(data.buffer, data.instant);
 
-        if !send_data_to_server(&mut fas_service, frametime, data, &info) {
+        if !send_data_to_server(&mut fas_service, frametime, session, data, &info) {
             return Ok(());
         }
 
+        if info.gc_timer.elapsed() > GC_INTERVAL {
+            for (buffer, session) in info.gc(GC_TIMEOUT) {
+                let _ = fas_service.removeBuffer(buffer as i64, session, info.pid);
+            }
+        }
+
         if IS_CHILD.load(Ordering::Acquire) {
             return Ok(());
         }
@@ -61,6 +68,7 @@ pub unsafe fn thread(process: String) -> Result<()> {
 fn send_data_to_server(
     fas_service: &mut Strong<dyn IRemoteService>,
     frametime: Duration,
+    session: i64,
     data: Data,
     info: &Info,
 ) -> bool {
@@ -69,13 +77,14 @@ fn send_data_to_server(
 
     fas_service.sendData(
          data.buffer as i64,
+         session,
          &info.process,
          info.pid,
         frametime.as_nanos() as i64,
         data.cpu
     ).map_or_else(|_| get_server_interface().map_or(false, |service| {
         *fas_service = service;
-        send_data_to_server(fas_service, frametime, data, info)
+        send_data_to_server(fas_service, frametime, session, data, info)
     }), |send| {
         #[cfg(debug_assertions)]
         {